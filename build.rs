@@ -1,13 +1,34 @@
 extern crate lzma;
 
+use std::cmp::Ordering;
 use std::env;
 use std::fs::File;
-use std::io::{Read, Write};
-use std::path::Path;
+use std::io::{BufRead, BufReader, Error, ErrorKind, Write};
+use std::path::{Path, PathBuf};
 
-use lzma::reader::LzmaReader;
+/// Environment variable which may be set to the path of a file listing the
+/// exact characters to embed, in place of those selected by cargo features.
+const SUBSET_VAR: &str = "SDL2_UNIFONT_SUBSET";
 
-/// Xzips unifont .hex files for embedding in executable
+/// Environment variable which may be set to a list of .hex files, separated
+/// like `PATH`, to embed in place of the bundled Unifont.
+const HEX_VAR: &str = "SDL2_UNIFONT_HEX";
+
+// Defines `GROUPS`, which the library also uses to suggest features
+include!("src/features.rs");
+
+/// Pre-parses the unifont .hex files (or those listed in `SDL2_UNIFONT_HEX`)
+/// into an xz-compressed binary glyph table for embedding in the executable,
+/// so that the font can be loaded at runtime without having to parse any text.
+/// Only the glyphs selected by the enabled cargo features are embedded, or if
+/// `SDL2_UNIFONT_SUBSET` is set, only those listed in the file that it names
+/// (see `read_subset`).
+///
+/// Each glyph in the table is stored as its code point (4 bytes, little
+/// endian), followed by its width in pixels (1 byte), followed by its 16 rows
+/// of pixels, each of which is `width / 8` bytes long, with the MSB as the
+/// leftmost pixel. `Font::load_table` reads this format back in, whilst
+/// `unifont::load` decompresses it.
 fn main() -> std::io::Result<()> {
     let out_dir = env::var("OUT_DIR").unwrap();
     let out_path = Path::new(&out_dir);
@@ -15,18 +36,267 @@ fn main() -> std::io::Result<()> {
     let project_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
     let data_dir = Path::new(&project_dir).join("data");
 
-    let files = ["unifont-11.0.02.hex", "unifont_upper-11.0.02.hex"];
+    println!("cargo:rerun-if-env-changed={}", SUBSET_VAR);
+    let subset = match env::var_os(SUBSET_VAR) {
+        Some(path) => Some(read_subset(Path::new(&path))?),
+        None => None,
+    };
+
+    let ranges = merge_ranges(match subset {
+        Some(ref ranges) => ranges.clone(),
+        None => GROUPS
+            .iter()
+            .filter(|(feature, _)| feature_enabled(feature))
+            .flat_map(|(_, ranges)| ranges.iter().cloned())
+            .collect(),
+    });
+
+    // A subset replaces the plane features too
+    let plane_0 = subset.is_none() && feature_enabled("plane-0");
+    let plane_1 = subset.is_none() && feature_enabled("plane-1");
+
+    // Lists the enabled features, for `unifont::feature_for`
+    let enabled: Vec<&str> = ["plane-0", "plane-1"]
+        .iter()
+        .cloned()
+        .chain(GROUPS.iter().map(|(feature, _)| *feature))
+        .filter(|feature| feature_enabled(feature))
+        .collect();
+    println!(
+        "cargo:rustc-env=SDL2_UNIFONT_FEATURES={}",
+        enabled.join(",")
+    );
+
+    // Each file, and whether all of it is embedded. Custom fonts may not be
+    // split by plane like Unifont is, so each of their glyphs is embedded
+    // according to the plane that it is in instead.
+    println!("cargo:rerun-if-env-changed={}", HEX_VAR);
+    let files: Vec<(PathBuf, Option<bool>)> = match env::var_os(HEX_VAR) {
+        Some(paths) => env::split_paths(&paths).map(|p| (p, None)).collect(),
+        None => vec![
+            (data_dir.join("unifont-11.0.02.hex"), Some(plane_0)),
+            (data_dir.join("unifont_upper-11.0.02.hex"), Some(plane_1)),
+        ],
+    };
+
+    // Describes the embedded font, for `Font::info`. Nothing is known about a
+    // custom font but the name of its first file, and empty values are
+    // treated as unknown.
+    let (name, version, license) = match env::var_os(HEX_VAR) {
+        Some(_) => {
+            let name = match files.first().and_then(|f| f.0.file_stem()) {
+                Some(stem) => stem.to_string_lossy().into_owned(),
+                None => String::new(),
+            };
+            (name, "", "")
+        }
+        None => (
+            "GNU Unifont".to_string(),
+            "11.0.02",
+            "GPL-2.0-or-later WITH Font-exception-2.0",
+        ),
+    };
+    println!("cargo:rustc-env=SDL2_UNIFONT_NAME={}", name);
+    println!("cargo:rustc-env=SDL2_UNIFONT_VERSION={}", version);
+    println!("cargo:rustc-env=SDL2_UNIFONT_LICENSE={}", license);
+
+    let mut table = Vec::new();
+
+    for (path, whole_file) in files.iter() {
+        println!("cargo:rerun-if-changed={}", path.display());
+
+        if *whole_file == Some(false) && ranges.is_empty() {
+            continue;
+        }
+
+        let reader = BufReader::new(File::open(path)?);
+
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+
+            if line.trim().is_empty() {
+                continue;
+            }
 
-    for f in files.iter() {
-        let handle = File::open(&data_dir.join(f))?;
-        let mut comp = LzmaReader::new_compressor(handle, 6).unwrap();
+            let (codepoint, bitmap) = match parse_line(&line) {
+                Ok(glyph) => glyph,
+                Err(reason) => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("{}:{}: {}", path.display(), i + 1, reason),
+                    ))
+                }
+            };
 
-        let mut content = Vec::new();
-        comp.read_to_end(&mut content)?;
+            let whole_plane = match *whole_file {
+                Some(whole) => whole,
+                None if codepoint <= 0xffff => plane_0,
+                None => plane_1,
+            };
 
-        let mut out = File::create(&out_path.join(f).with_extension("hex.xz"))?;
-        out.write_all(&content)?;
+            if !whole_plane && !in_ranges(&ranges, codepoint) {
+                continue;
+            }
+
+            table.extend_from_slice(&codepoint.to_le_bytes());
+            table.push((bitmap.len() / 16 * 8) as u8);
+            table.extend_from_slice(&bitmap);
+        }
     }
 
+    // Compressing the table makes it less than a third of the size
+    let table = match lzma::compress(&table, 6) {
+        Ok(compressed) => compressed,
+        Err(err) => return Err(Error::new(ErrorKind::Other, err.to_string())),
+    };
+
+    let mut out = File::create(out_path.join("unifont.glyphs.xz"))?;
+    out.write_all(&table)?;
+
     Ok(())
 }
+
+/// Returns whether the named cargo feature is enabled for this build.
+fn feature_enabled(feature: &str) -> bool {
+    let var =
+        format!("CARGO_FEATURE_{}", feature.to_uppercase().replace('-', "_"));
+
+    env::var_os(var).is_some()
+}
+
+/// Reads the file listing the characters to embed. Each line contains
+/// characters to embed as they are, and/or `U+XXXX` code points and
+/// `U+XXXX..U+YYYY` ranges, separated by whitespace. Lines starting with `#`
+/// are comments. The space character is always embedded.
+fn read_subset(path: &Path) -> std::io::Result<Vec<(u32, u32)>> {
+    println!("cargo:rerun-if-changed={}", path.display());
+
+    let reader = BufReader::new(File::open(path)?);
+    let mut ranges = vec![(0x20, 0x20)];
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+
+        if line.trim_start().starts_with('#') {
+            continue;
+        }
+
+        for token in line.split_whitespace() {
+            if !token.starts_with("U+") {
+                ranges.extend(token.chars().map(|c| (c as u32, c as u32)));
+                continue;
+            }
+
+            match parse_range(token) {
+                Some(range) => ranges.push(range),
+                None => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!(
+                            "{}:{}: invalid code point range: {}",
+                            path.display(),
+                            i + 1,
+                            token
+                        ),
+                    ))
+                }
+            }
+        }
+    }
+
+    Ok(ranges)
+}
+
+/// Parses a `U+XXXX` code point or `U+XXXX..U+YYYY` range from a subset file.
+fn parse_range(token: &str) -> Option<(u32, u32)> {
+    let parse = |cp: &str| match cp.strip_prefix("U+") {
+        Some(digits) => u32::from_str_radix(digits, 16).ok(),
+        None => None,
+    };
+
+    let mut split = token.splitn(2, "..");
+    let lo = parse(split.next()?)?;
+    let hi = match split.next() {
+        Some(hi) => parse(hi)?,
+        None => lo,
+    };
+
+    if lo > hi {
+        return None;
+    }
+
+    Some((lo, hi))
+}
+
+/// Sorts a list of inclusive code point ranges, and merges any which overlap
+/// or touch, so that it can be searched by `in_ranges`.
+fn merge_ranges(mut ranges: Vec<(u32, u32)>) -> Vec<(u32, u32)> {
+    ranges.sort_unstable();
+
+    let mut merged: Vec<(u32, u32)> = Vec::with_capacity(ranges.len());
+    for (lo, hi) in ranges {
+        match merged.last_mut() {
+            Some(last) if lo <= last.1.saturating_add(1) => {
+                last.1 = last.1.max(hi)
+            }
+            _ => merged.push((lo, hi)),
+        }
+    }
+
+    merged
+}
+
+/// Returns whether a code point falls within any of a list of merged ranges.
+fn in_ranges(ranges: &[(u32, u32)], codepoint: u32) -> bool {
+    ranges
+        .binary_search_by(|&(lo, hi)| {
+            if hi < codepoint {
+                Ordering::Less
+            } else if lo > codepoint {
+                Ordering::Greater
+            } else {
+                Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+/// Parses a single `CODEPOINT:BITMAP` line of a .hex file, returning the code
+/// point and the bitmap's bytes. Follows the same rules as `font::parse_hex`,
+/// and returns the reason that the line is malformed if it breaks them.
+fn parse_line(line: &str) -> Result<(u32, Vec<u8>), &'static str> {
+    let mut split = line.trim().split(':');
+
+    let codepoint = match split.next().map(|cp| u32::from_str_radix(cp, 16)) {
+        Some(Ok(cp)) if cp <= 0x10ffff => cp,
+        Some(Ok(_)) => return Err("code point out of range"),
+        _ => return Err("invalid code point"),
+    };
+
+    let digits = match split.next() {
+        Some(digits) => digits,
+        None => return Err("missing bitmap"),
+    };
+
+    if split.next().is_some() {
+        return Err("unexpected data after bitmap");
+    }
+
+    // Characters may be 8, 16, 24 or 32 pixels wide
+    if !matches!(digits.len(), 32 | 64 | 96 | 128) {
+        return Err("bitmap is not 32, 64, 96 or 128 digits");
+    }
+
+    let mut bitmap = Vec::with_capacity(digits.len() / 2);
+    for i in (0..digits.len()).step_by(2) {
+        match digits
+            .get(i..i + 2)
+            .map(|byte| u8::from_str_radix(byte, 16))
+        {
+            Some(Ok(byte)) => bitmap.push(byte),
+            _ => return Err("invalid bitmap digits"),
+        }
+    }
+
+    Ok((codepoint, bitmap))
+}
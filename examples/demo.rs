@@ -96,8 +96,8 @@ fn draw_demo<'a>(
     renderer.scale = 3;
     // Cycle through colours
     for (i, colour) in COLOURS[iter_num % nc..nc]
-        .into_iter()
-        .chain(COLOURS[0..iter_num % nc].into_iter())
+        .iter()
+        .chain(COLOURS[0..iter_num % nc].iter())
         .enumerate()
     {
         // Text colour can be changed per-draw operation
@@ -240,19 +240,13 @@ fn draw_demo<'a>(
                     &last_time.subsec_millis().to_string(),
                 );
 
-                // Render formatted string
+                // Render formatted string straight into the screen surface,
+                // rather than drawing to a new surface and blitting it
                 renderer
-                    .draw()
-                    .unwrap()
-                    .blit(
-                        None,
+                    .draw_to(
                         &mut screen,
-                        Rect::new(
-                            798 - renderer.measure_width().unwrap() as i32,
-                            600 - 20,
-                            0,
-                            0,
-                        ),
+                        798 - renderer.measure_width().unwrap() as i32,
+                        600 - 20,
                     ).unwrap();
             }
         }
@@ -285,7 +279,7 @@ fn main() {
     let mut canvas = window.into_canvas().build().unwrap();
     let texture_creator = canvas.texture_creator();
 
-    // Window stays black whilst font decompression / parsing occurs
+    // Window stays black whilst the font is loaded
     canvas.set_draw_color(Color::RGB(0, 0, 0));
     canvas.clear();
     canvas.present();
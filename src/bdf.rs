@@ -0,0 +1,360 @@
+/// Parsing of Glyph Bitmap Distribution Format (BDF) fonts, such as Terminus,
+/// Cozette and the X11 misc-fixed fonts, into `Font`s.
+///
+/// Each character of a BDF font has its own bounding box (`BBX`), positioned
+/// relative to the character's origin on the baseline, and its own horizontal
+/// advance (`DWIDTH`). Characters are drawn into cells which are as tall as the
+/// font's ascent plus its descent, with the baseline `ascent` rows from the
+/// top, and as wide as the character's advance, so that text is positioned
+/// the same way as it would be by any other BDF renderer. Anything outside of
+/// a character's cell is clipped.
+use std::io::BufRead;
+
+use error::Error;
+use font::{Font, FontInfo};
+use glyph::{Glyph, MAX_WIDTH};
+
+/// A bounding box, as the width, height, and x and y offsets of its bottom-left
+/// corner from the origin.
+#[derive(Clone, Copy)]
+struct BoundingBox {
+    width: i32,
+    height: i32,
+    x_offset: i32,
+    y_offset: i32,
+}
+
+/// Parses a complete BDF font.
+pub fn parse_bdf<B: BufRead>(reader: B) -> Result<Font, Error> {
+    // Font-wide metrics, which must all come before the first character
+    let mut ascent: Option<i32> = None;
+    let mut descent: Option<i32> = None;
+    let mut font_bbx: Option<BoundingBox> = None;
+    let mut font_advance: Option<i32> = None;
+
+    // Created once the metrics are known
+    let mut font: Option<Font> = None;
+    let mut info = FontInfo::default();
+    // Name of the font as an X logical font description, used if the font has
+    // no family name
+    let mut xlfd: Option<String> = None;
+
+    // The character currently being parsed
+    let mut encoding: Option<u32> = None;
+    let mut advance: Option<i32> = None;
+    let mut bbx: Option<BoundingBox> = None;
+    // Only set between `BITMAP` and `ENDCHAR`
+    let mut rows: Option<Vec<Vec<u8>>> = None;
+
+    let mut line_count = 0;
+
+    for (line_i, l) in reader.lines().enumerate() {
+        let line = l?;
+        line_count = line_i + 1;
+        let line = line.trim();
+
+        // Returned as the error value if the current line is malformed
+        let malformed = |reason: &str| Error::MalformedBdf {
+            line: line_i + 1,
+            reason: reason.to_string(),
+        };
+
+        if line != "ENDCHAR" {
+            if let Some(ref mut rows) = rows {
+                match parse_row(line) {
+                    Some(row) => rows.push(row),
+                    None => return Err(malformed("invalid bitmap digits")),
+                }
+                continue;
+            }
+        }
+
+        let mut words = line.split_whitespace();
+        let keyword = match words.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let numbers: Vec<i32> = words.filter_map(|w| w.parse().ok()).collect();
+
+        match keyword {
+            "FONT" => xlfd = Some(parse_string(line, keyword)),
+            "FAMILY_NAME" => info.name = Some(parse_string(line, keyword)),
+            "FONT_VERSION" => info.version = Some(parse_string(line, keyword)),
+            "COPYRIGHT" => info.license = Some(parse_string(line, keyword)),
+            "FONT_ASCENT" => match numbers.first() {
+                Some(&n) => ascent = Some(n),
+                None => return Err(malformed("missing ascent")),
+            },
+            "FONT_DESCENT" => match numbers.first() {
+                Some(&n) => descent = Some(n),
+                None => return Err(malformed("missing descent")),
+            },
+            "FONTBOUNDINGBOX" => match parse_bbx(&numbers) {
+                Some(b) => font_bbx = Some(b),
+                None => return Err(malformed("invalid bounding box")),
+            },
+            "STARTCHAR" => {
+                if font.is_none() {
+                    font = Some(match create_font(ascent, descent, font_bbx) {
+                        Ok(font) => font,
+                        Err(reason) => return Err(malformed(reason)),
+                    });
+                }
+
+                encoding = None;
+                advance = None;
+                bbx = None;
+            }
+            "ENCODING" => {
+                // Characters outside of the standard encoding (-1) may give an
+                // alternative code point
+                encoding = match numbers.as_slice() {
+                    [-1, alt, ..] | [alt, ..] if *alt >= 0 => Some(*alt as u32),
+                    [_, ..] => None,
+                    [] => return Err(malformed("missing encoding")),
+                };
+            }
+            "DWIDTH" => match numbers.first() {
+                Some(&n) if font.is_none() => font_advance = Some(n),
+                Some(&n) => advance = Some(n),
+                None => return Err(malformed("missing advance")),
+            },
+            "BBX" => match parse_bbx(&numbers) {
+                Some(b) => bbx = Some(b),
+                None => return Err(malformed("invalid bounding box")),
+            },
+            "BITMAP" => rows = Some(Vec::new()),
+            "ENDCHAR" => {
+                let bitmap = match rows.take() {
+                    Some(rows) => rows,
+                    None => return Err(malformed("ENDCHAR before BITMAP")),
+                };
+                let bbx = match bbx.or(font_bbx) {
+                    Some(bbx) => bbx,
+                    None => return Err(malformed("missing bounding box")),
+                };
+
+                let font = match font {
+                    Some(ref mut font) => font,
+                    None => return Err(malformed("ENDCHAR before STARTCHAR")),
+                };
+
+                // Characters which can't be represented in Unicode are dropped
+                if let Some(codepoint) = encoding.filter(|&cp| cp <= 0x10ffff) {
+                    let advance = advance.or(font_advance);
+                    draw_char(font, codepoint, advance, bbx, &bitmap)?;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut font = match font {
+        Some(font) => font,
+        // A font without any characters is still valid
+        None => match create_font(ascent, descent, font_bbx) {
+            Ok(font) => font,
+            Err(reason) => {
+                return Err(Error::MalformedBdf {
+                    line: line_count,
+                    reason: reason.to_string(),
+                })
+            }
+        },
+    };
+
+    if info.name.is_none() {
+        info.name = xlfd;
+    }
+    font.set_info(info);
+
+    Ok(font)
+}
+
+/// Returns the value of a line which holds a single string, such as a property,
+/// given the line's keyword. Values in quotes have the quotes removed, and any
+/// doubled quotes within them undoubled.
+fn parse_string(line: &str, keyword: &str) -> String {
+    let value = line[keyword.len()..].trim();
+
+    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        value[1..value.len() - 1].replace("\"\"", "\"")
+    } else {
+        value.to_string()
+    }
+}
+
+/// Creates an empty font from the font-wide metrics. The ascent and descent
+/// are taken from the font's bounding box, if they aren't given explicitly.
+fn create_font(
+    ascent: Option<i32>,
+    descent: Option<i32>,
+    bbx: Option<BoundingBox>,
+) -> Result<Font, &'static str> {
+    let (ascent, descent) = match (ascent, descent, bbx) {
+        (Some(ascent), Some(descent), _) => (ascent, descent),
+        (_, _, Some(bbx)) => (bbx.height + bbx.y_offset, -bbx.y_offset),
+        _ => {
+            return Err("missing FONT_ASCENT, FONT_DESCENT and FONTBOUNDINGBOX")
+        }
+    };
+
+    let height = ascent + descent;
+    if height <= 0 || height > 255 || ascent < 0 || descent < 0 {
+        return Err("font height out of range");
+    }
+
+    let mut font = Font::with_height(height as u8);
+    font.set_ascent(ascent as u8);
+
+    Ok(font)
+}
+
+/// Draws a character's bitmap into a cell of the font's height, and as wide
+/// as the character's advance, then inserts it into the font.
+fn draw_char(
+    font: &mut Font,
+    codepoint: u32,
+    advance: Option<i32>,
+    bbx: BoundingBox,
+    bitmap: &[Vec<u8>],
+) -> Result<(), Error> {
+    // Zero-width characters (e.g. combining marks) can't be overlaid onto the
+    // previous character, so are given enough width to be seen instead
+    let extent = bbx.x_offset + bbx.width;
+    let width = match advance {
+        Some(advance) if advance > 0 => advance,
+        _ => extent,
+    };
+    let width = width.clamp(1, MAX_WIDTH as i32);
+
+    let height = font.height() as i32;
+    let row_bytes = (width as usize).div_ceil(8);
+    let mut cell = vec![0; row_bytes * height as usize];
+
+    // Cell row of the top of the bounding box
+    let top = font.ascent() as i32 - (bbx.y_offset + bbx.height);
+
+    for (r, row) in bitmap.iter().take(bbx.height as usize).enumerate() {
+        let y = top + r as i32;
+        if y < 0 || y >= height {
+            continue;
+        }
+
+        for col in 0..bbx.width {
+            let set = match row.get(col as usize / 8) {
+                Some(byte) => byte & (0x80 >> (col % 8)) != 0,
+                None => false,
+            };
+
+            let x = bbx.x_offset + col;
+            if set && x >= 0 && x < width {
+                cell[y as usize * row_bytes + x as usize / 8] |=
+                    0x80 >> (x % 8);
+            }
+        }
+    }
+
+    font.insert(codepoint, Glyph::new(width as u8, &cell))
+}
+
+/// Parses the four numbers of a `BBX` or `FONTBOUNDINGBOX` line.
+fn parse_bbx(numbers: &[i32]) -> Option<BoundingBox> {
+    match *numbers {
+        [width, height, x_offset, y_offset] if width >= 0 && height >= 0 => {
+            Some(BoundingBox {
+                width,
+                height,
+                x_offset,
+                y_offset,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Parses one row of a character's bitmap, which is a whole number of bytes in
+/// hex, with the MSB of the first byte as the leftmost pixel.
+fn parse_row(line: &str) -> Option<Vec<u8>> {
+    // A trailing odd digit fails to parse, rejecting the row
+    (0..line.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(line.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses a BDF font with the given header lines and characters, 6px tall
+    /// with its baseline 4px from the top.
+    fn parse(header: &str, chars: &str) -> Font {
+        let bdf = format!(
+            "STARTFONT 2.1\nFONT test\nSIZE 6 75 75\n\
+             FONTBOUNDINGBOX 4 6 0 -2\nFONT_ASCENT 4\nFONT_DESCENT 2\n\
+             {}CHARS 1\n{}ENDFONT\n",
+            header, chars
+        );
+
+        parse_bdf(bdf.as_bytes()).unwrap()
+    }
+
+    /// Returns the pixels of a glyph as strings of `#` and `.`, one per row.
+    fn pixels(font: &Font, codepoint: u32) -> Vec<String> {
+        let glyph = font.get(codepoint).unwrap();
+
+        (0..glyph.height() as u32)
+            .map(|y| {
+                (0..glyph.width() as u32)
+                    .map(|x| if glyph.pixel(x, y) { '#' } else { '.' })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn places_descender_below_baseline() {
+        // A 2x3 box, one pixel right of the origin and two pixels below it
+        let font = parse(
+            "",
+            "STARTCHAR g\nENCODING 103\nDWIDTH 4 0\nBBX 2 3 1 -2\n\
+             BITMAP\nC0\n40\n80\nENDCHAR\n",
+        );
+
+        assert_eq!(font.ascent(), 4);
+        assert_eq!(
+            pixels(&font, 'g' as u32),
+            ["....", "....", "....", ".##.", "..#.", ".#.."]
+        );
+    }
+
+    #[test]
+    fn uses_alternative_encoding() {
+        let font = parse(
+            "",
+            "STARTCHAR alt\nENCODING -1 65\nDWIDTH 4 0\nBBX 1 1 0 0\n\
+             BITMAP\n80\nENDCHAR\n\
+             STARTCHAR none\nENCODING -1\nDWIDTH 4 0\nBBX 1 1 0 0\n\
+             BITMAP\n80\nENDCHAR\n",
+        );
+
+        assert_eq!(font.chars().len(), 1);
+        assert_eq!(pixels(&font, 0x41)[3], "#...");
+    }
+
+    #[test]
+    fn uses_font_advance() {
+        // Characters without their own DWIDTH use the font's
+        let font = parse(
+            "DWIDTH 6 0\n",
+            "STARTCHAR a\nENCODING 97\nBBX 1 1 5 0\nBITMAP\n80\nENDCHAR\n\
+             STARTCHAR b\nENCODING 98\nDWIDTH 3 0\nBBX 1 1 0 0\n\
+             BITMAP\n80\nENDCHAR\n",
+        );
+
+        assert_eq!(font.width('a'), Some(6));
+        assert_eq!(pixels(&font, 'a' as u32)[3], ".....#");
+        assert_eq!(font.width('b'), Some(3));
+    }
+}
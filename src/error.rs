@@ -0,0 +1,135 @@
+/// The error type shared by font loading and rendering, so that callers can
+/// match on specific failures (such as a missing character) and recover.
+use std::error;
+use std::fmt;
+use std::io;
+
+use lzma::LzmaError;
+use sdl2::pixels::PixelFormatEnum;
+
+/// Describes everything that can go wrong whilst loading a font, or drawing
+/// text with one.
+#[derive(Debug)]
+pub enum Error {
+    /// The text contains a character which is not defined in the font (i.e.
+    /// the feature to include it was probably not enabled).
+    MissingGlyph {
+        /// The character which could not be found
+        character: char,
+        /// Byte index of the character within the text being drawn or measured
+        index: usize,
+    },
+    /// A compressed font could not be decompressed.
+    Decompression(LzmaError),
+    /// A font could not be read.
+    Io(io::Error),
+    /// A line of a .hex font file could not be parsed.
+    MalformedHex {
+        /// Line number of the malformed line, counting from 1
+        line: usize,
+        /// Describes what is wrong with the line
+        reason: String,
+    },
+    /// A line of a BDF font file could not be parsed.
+    MalformedBdf {
+        /// Line number of the malformed line, counting from 1
+        line: usize,
+        /// Describes what is wrong with the line
+        reason: String,
+    },
+    /// A PSF font file could not be parsed.
+    MalformedPsf(String),
+    /// A character can't be added to a font, because its code point is above
+    /// U+10FFFF, or it is too wide, or isn't the same height as the font.
+    InvalidGlyph {
+        /// Code point that the character was being added as
+        codepoint: u32,
+        /// Width of the character, in pixels
+        width: u32,
+        /// Height of the character, in pixels
+        height: u32,
+    },
+    /// A glyph can't be created from a bitmap, because the width is 0 or above
+    /// 32px, or the bitmap doesn't contain between 1 and 255 whole rows.
+    InvalidBitmap {
+        /// Width of the glyph, in pixels
+        width: u32,
+        /// Length of the bitmap, in bytes
+        len: usize,
+    },
+    /// SDL failed to create, fill or blit a surface.
+    Sdl(String),
+    /// Text can't be drawn directly into surfaces with this pixel format.
+    UnsupportedPixelFormat(PixelFormatEnum),
+    /// A `FormattedRenderer` variable was drawn or measured without having
+    /// been added first.
+    UndefinedVariable(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::MissingGlyph { character, index } => write!(
+                f,
+                "font does not contain {} (code point: 0x{:x}, byte index: {})",
+                character, character as u32, index
+            ),
+            Error::Decompression(ref err) => {
+                write!(f, "failed to decompress font: {}", err)
+            }
+            Error::Io(ref err) => write!(f, "failed to read font: {}", err),
+            Error::MalformedHex { line, ref reason } => {
+                write!(f, "malformed .hex font on line {}: {}", line, reason)
+            }
+            Error::MalformedBdf { line, ref reason } => {
+                write!(f, "malformed BDF font on line {}: {}", line, reason)
+            }
+            Error::MalformedPsf(ref reason) => {
+                write!(f, "malformed PSF font: {}", reason)
+            }
+            Error::InvalidGlyph {
+                codepoint,
+                width,
+                height,
+            } => write!(
+                f,
+                "can't add a {}x{} glyph to the font as code point 0x{:x}",
+                width, height, codepoint
+            ),
+            Error::InvalidBitmap { width, len } => write!(
+                f,
+                "can't make a {}px wide glyph from a {} byte bitmap",
+                width, len
+            ),
+            Error::Sdl(ref err) => write!(f, "SDL error: {}", err),
+            Error::UnsupportedPixelFormat(format) => {
+                write!(f, "unsupported pixel format: {:?}", format)
+            }
+            Error::UndefinedVariable(ref name) => {
+                write!(f, "undefined variable used: {}", name)
+            }
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Decompression(ref err) => Some(err),
+            Error::Io(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<LzmaError> for Error {
+    fn from(err: LzmaError) -> Error {
+        Error::Decompression(err)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
@@ -0,0 +1,63 @@
+/// Groups of scripts which can be individually selected with cargo features,
+/// as the feature name and the code point ranges which it embeds. The plane
+/// features include everything in the corresponding .hex file, whether or not
+/// it falls into one of these groups.
+pub const GROUPS: [(&str, &[(u32, u32)]); 8] = [
+    (
+        "latin",
+        &[
+            (0x0000, 0x036f),
+            (0x1d00, 0x1dff),
+            (0x1e00, 0x1eff),
+            // General Punctuation, including the `…` that `truncate` inserts,
+            // and Currency Symbols
+            (0x2000, 0x206f),
+            (0x20a0, 0x20cf),
+            (0x2c60, 0x2c7f),
+            (0xa720, 0xa7ff),
+            (0xab30, 0xab6f),
+            (0xfb00, 0xfb06),
+        ],
+    ),
+    ("greek", &[(0x0370, 0x03ff), (0x1f00, 0x1fff)]),
+    (
+        "cyrillic",
+        &[
+            (0x0400, 0x052f),
+            (0x1c80, 0x1c8f),
+            (0x2de0, 0x2dff),
+            (0xa640, 0xa69f),
+        ],
+    ),
+    ("box-drawing", &[(0x2500, 0x259f)]),
+    (
+        "symbols",
+        &[
+            (0x2070, 0x209f),
+            (0x20d0, 0x24ff),
+            (0x25a0, 0x2bff),
+            (0x2e00, 0x2e7f),
+        ],
+    ),
+    (
+        "cjk",
+        &[
+            (0x2e80, 0x2fff),
+            (0x3000, 0x312f),
+            (0x3190, 0x9fff),
+            (0xf900, 0xfaff),
+            (0xfe30, 0xfe4f),
+            (0xff00, 0xffef),
+        ],
+    ),
+    (
+        "hangul",
+        &[
+            (0x1100, 0x11ff),
+            (0x3130, 0x318f),
+            (0xa960, 0xa97f),
+            (0xac00, 0xd7ff),
+        ],
+    ),
+    ("emoji", &[(0x1f000, 0x1faff)]),
+];
@@ -0,0 +1,545 @@
+/// Parsing of GNU Unifont .hex font files into `Font`s, which can be used by
+/// the renderers in place of the embedded Unifont, and writing `Font`s back
+/// out as .hex files.
+///
+/// A .hex file contains one character per line, in the form
+/// `CODEPOINT:BITMAP`, where both fields are hexadecimal, and the bitmap is
+/// either 32 (half-width, 8x16) or 64 (full-width, 16x16) digits long. The 96
+/// and 128 digit (24x16 and 32x16) glyphs of newer Unifont releases and other
+/// fonts are supported too. Fonts which aren't 16px tall can also be stored in
+/// the format, with 2, 4, 6 or 8 digits per row, for 8, 16, 24 or 32px wide
+/// glyphs.
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use lzma::LzmaError;
+use lzma::LzmaReader;
+
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::surface::SurfaceRef;
+
+use bdf;
+use blocks::{self, Block};
+use error::Error;
+use glyph::MAX_WIDTH;
+use psf;
+
+pub use glyph::{FontChar, FontChars, Glyph};
+
+/// The magic bytes at the start of every xz stream, used to detect whether a
+/// supplied font needs decompressing before it is parsed.
+const XZ_MAGIC: [u8; 6] = [0xfd, b'7', b'z', b'X', b'Z', 0x00];
+
+/// A complete set of character definitions which text can be rendered with.
+/// The embedded Unifont is itself a `Font` (see `unifont::get_unifont`), but
+/// any other font in the .hex, BDF or PSF formats can be loaded at runtime too.
+pub struct Font {
+    /// Contains parsed character definitions. A page table is utilised for
+    /// fast look-ups, since there are often gaps between defined characters.
+    chars: FontChars,
+    /// Number of rows of each character above the baseline
+    ascent: u8,
+    /// Describes the font
+    info: FontInfo,
+}
+
+/// Descriptive information about a font. Any of it may be unknown, since few
+/// font formats record it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FontInfo {
+    /// The font's name, e.g. "GNU Unifont"
+    pub name: Option<String>,
+    /// The version of the font, e.g. "11.0.02"
+    pub version: Option<String>,
+    /// The licence which the font is distributed under, preferably as an SPDX
+    /// expression, or the font's copyright notice
+    pub license: Option<String>,
+}
+
+/// The number of characters which a font defines in one Unicode block, as
+/// returned by `Font::block_coverage`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockCoverage {
+    /// The block in question
+    pub block: Block,
+    /// Number of the block's code points which the font defines
+    pub covered: u32,
+}
+
+impl Font {
+    /// Creates a 16px tall font which doesn't contain any characters.
+    pub fn new() -> Font {
+        Font::with_height(16)
+    }
+
+    /// Creates a font of the given height which doesn't contain any
+    /// characters, which other .hex fonts of the same height can be loaded
+    /// into using `load_reader`, or which characters can be added to with
+    /// `insert`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the height is 0.
+    pub fn with_height(height: u8) -> Font {
+        Font {
+            chars: FontChars::with_height(height),
+            // Unifont's baseline is 2px from the bottom of its 16px cells
+            ascent: height - height / 8,
+            info: FontInfo::default(),
+        }
+    }
+
+    /// Reads a .hex font from the supplied reader. The font may either be
+    /// plain text, or xz-compressed, which is detected automatically.
+    pub fn from_reader<R: Read>(reader: R) -> Result<Font, Error> {
+        let mut font = Font::new();
+        font.load_reader(reader)?;
+        Ok(font)
+    }
+
+    /// Opens and reads the .hex (or .hex.xz) font file at the supplied path.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Font, Error> {
+        Font::from_reader(File::open(path)?)
+    }
+
+    /// Parses the contents of a plain text .hex font file.
+    pub fn from_hex_str(hex: &str) -> Result<Font, Error> {
+        let mut font = Font::new();
+        parse_hex(&mut font.chars, hex.as_bytes())?;
+        Ok(font)
+    }
+
+    /// Reads a BDF font from the supplied reader.
+    pub fn from_bdf_reader<R: Read>(reader: R) -> Result<Font, Error> {
+        bdf::parse_bdf(BufReader::new(reader))
+    }
+
+    /// Opens and reads the BDF font file at the supplied path.
+    pub fn from_bdf_path<P: AsRef<Path>>(path: P) -> Result<Font, Error> {
+        Font::from_bdf_reader(File::open(path)?)
+    }
+
+    /// Parses the contents of a BDF font file.
+    pub fn from_bdf_str(bdf: &str) -> Result<Font, Error> {
+        bdf::parse_bdf(bdf.as_bytes())
+    }
+
+    /// Writes the font in the canonical .hex format used by GNU Unifont: one
+    /// line per character, in code point order, with uppercase hex digits, and
+    /// code points padded to 4 digits (or 6, above U+FFFF). Reading one of
+    /// Unifont's own .hex files and writing it back out produces an identical
+    /// file. Characters whose width isn't a multiple of 8px are padded, since
+    /// the format can't describe them.
+    pub fn write_hex<W: Write>(&self, writer: W) -> Result<(), Error> {
+        write_hex(&self.chars, writer)
+    }
+
+    /// Reads a PSF1 or PSF2 console font from the supplied reader. The font may
+    /// either be uncompressed, or gzipped (.psf.gz), which is detected
+    /// automatically. If the font has a Unicode table, each glyph is defined
+    /// for each of the code points in its entry, otherwise each glyph is
+    /// defined for the code point equal to its index.
+    pub fn from_psf_reader<R: Read>(reader: R) -> Result<Font, Error> {
+        psf::read_psf(reader)
+    }
+
+    /// Opens and reads the PSF (or .psf.gz) font file at the supplied path.
+    pub fn from_psf_path<P: AsRef<Path>>(path: P) -> Result<Font, Error> {
+        Font::from_psf_reader(File::open(path)?)
+    }
+
+    /// Writes the font to the supplied writer as a PSF2 console font, with a
+    /// Unicode table mapping each glyph to the code points it displays. Every
+    /// glyph is as wide as the font's widest character, with narrower
+    /// characters drawn at the left, and characters which look identical share
+    /// a glyph. Build a `Font` containing only the characters you need (e.g.
+    /// with `insert`) to write a subset of a larger font.
+    pub fn write_psf<W: Write>(&self, writer: W) -> Result<(), Error> {
+        psf::write_psf2(self, writer)
+    }
+
+    /// Reads a further .hex font from the supplied reader into this font. Any
+    /// characters which are already defined are replaced.
+    pub fn load_reader<R: Read>(&mut self, reader: R) -> Result<(), Error> {
+        let mut reader = BufReader::new(reader);
+
+        // Peek at the start of the font, without consuming anything
+        let compressed = reader.fill_buf()?.starts_with(&XZ_MAGIC);
+
+        if compressed {
+            let decomp = LzmaReader::new_decompressor(reader)?;
+
+            // Errors reading from the decompressor are decompression errors
+            match parse_hex(&mut self.chars, BufReader::new(decomp)) {
+                Err(Error::Io(err)) => {
+                    Err(Error::Decompression(LzmaError::Io(err)))
+                }
+                result => result,
+            }
+        } else {
+            parse_hex(&mut self.chars, reader)
+        }
+    }
+
+    /// Defines a single character, replacing any existing definition of it.
+    /// Returns `Error::InvalidGlyph` if the code point is above U+10FFFF, or
+    /// the character isn't the same height as the font.
+    pub fn insert(
+        &mut self,
+        codepoint: u32,
+        glyph: Glyph,
+    ) -> Result<(), Error> {
+        self.chars.insert(codepoint, glyph)
+    }
+
+    /// Parses further lines of a .hex font into this font, e.g. to add a few
+    /// icons to it. Any characters which are already defined are replaced.
+    pub fn load_hex_str(&mut self, hex: &str) -> Result<(), Error> {
+        parse_hex(&mut self.chars, hex.as_bytes())
+    }
+
+    /// Defines a single character from the contents of an SDL surface, which
+    /// must be as tall as the font, and up to 32px wide. Pixels which are at
+    /// least half opaque are set, or for surfaces without an alpha channel,
+    /// pixels which aren't black. Any existing definition of the character is
+    /// replaced.
+    pub fn insert_surface(
+        &mut self,
+        codepoint: u32,
+        surf: &SurfaceRef,
+    ) -> Result<(), Error> {
+        let (width, height) = (surf.width(), surf.height());
+
+        if width == 0
+            || width > MAX_WIDTH as u32
+            || height != self.height() as u32
+            || codepoint > 0x10ffff
+        {
+            return Err(Error::InvalidGlyph {
+                codepoint,
+                width,
+                height,
+            });
+        }
+
+        let has_alpha = surf.pixel_format_enum().supports_alpha();
+        let converted = surf
+            .convert_format(PixelFormatEnum::ARGB8888)
+            .map_err(Error::Sdl)?;
+        let pitch = converted.pitch() as usize;
+
+        let row_bytes = (width as usize).div_ceil(8);
+        let mut bitmap = vec![0; row_bytes * height as usize];
+
+        converted.with_lock(|pixels| {
+            for y in 0..height as usize {
+                for x in 0..width as usize {
+                    let i = y * pitch + x * 4;
+                    let argb = u32::from_ne_bytes([
+                        pixels[i],
+                        pixels[i + 1],
+                        pixels[i + 2],
+                        pixels[i + 3],
+                    ]);
+
+                    let set = if has_alpha {
+                        argb >> 24 >= 0x80
+                    } else {
+                        argb & 0xffffff != 0
+                    };
+
+                    if set {
+                        bitmap[y * row_bytes + x / 8] |= 0x80 >> (x % 8);
+                    }
+                }
+            }
+        });
+
+        self.chars
+            .insert(codepoint, Glyph::new(width as u8, &bitmap))
+    }
+
+    /// Reads a binary glyph table, as generated by build.rs for the embedded
+    /// Unifont, into this font. Each glyph is stored as its code point (4
+    /// bytes, little endian), its width in pixels (1 byte), and its 16 rows of
+    /// pixels, each `width / 8` bytes long, with the MSB as the leftmost pixel.
+    /// Glyphs are read one at a time, so the table can be decompressed as it's
+    /// read, and `progress` is called after each one.
+    pub(crate) fn load_table<R, F>(
+        &mut self,
+        mut table: R,
+        mut progress: F,
+    ) -> Result<(), Error>
+    where
+        R: Read,
+        F: FnMut(),
+    {
+        let mut header = [0; 5];
+        let mut bitmap = Vec::new();
+
+        loop {
+            // The table ends after the last glyph's bitmap
+            if table.read(&mut header[..1])? == 0 {
+                return Ok(());
+            }
+            table.read_exact(&mut header[1..])?;
+
+            let codepoint = u32::from_le_bytes([
+                header[0], header[1], header[2], header[3],
+            ]);
+            let width = header[4];
+            let len = (width as usize).div_ceil(8) * self.height() as usize;
+
+            bitmap.resize(len, 0);
+            table.read_exact(&mut bitmap)?;
+
+            self.chars.insert(codepoint, Glyph::new(width, &bitmap))?;
+            progress();
+        }
+    }
+
+    /// Looks up the definition of a single character.
+    pub fn get(&self, codepoint: u32) -> Option<Glyph<'_>> {
+        self.chars.get(codepoint)
+    }
+
+    /// Returns the height of every character in the font, in pixels.
+    pub fn height(&self) -> u8 {
+        self.chars.height()
+    }
+
+    /// Returns the number of rows of each character above the baseline, with
+    /// the rest of the rows being below it. Unifont's ascent is 14.
+    pub fn ascent(&self) -> u8 {
+        self.ascent
+    }
+
+    /// Sets the number of rows of each character above the baseline. By
+    /// default, the baseline is an eighth of the font's height from the
+    /// bottom, as it is in Unifont.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the ascent is greater than the font's height.
+    pub fn set_ascent(&mut self, ascent: u8) {
+        assert!(ascent <= self.height(), "ascent is greater than the height");
+
+        self.ascent = ascent;
+    }
+
+    /// Returns whether the font defines a character.
+    pub fn contains(&self, c: char) -> bool {
+        self.chars.contains(c as u32)
+    }
+
+    /// Returns the width of a character in pixels, or `None` if the font
+    /// doesn't define it.
+    pub fn width(&self, c: char) -> Option<u8> {
+        self.get(c as u32).map(|glyph| glyph.width())
+    }
+
+    /// Returns an iterator over every character which the font defines, in
+    /// code point order.
+    pub fn characters(&self) -> impl Iterator<Item = char> + '_ {
+        self.chars.iter().filter_map(|(cp, _)| char::from_u32(cp))
+    }
+
+    /// Counts how many characters the font defines in each Unicode block,
+    /// returning the blocks which it defines any characters in, in code point
+    /// order. Characters outside of every block aren't counted.
+    pub fn block_coverage(&self) -> Vec<BlockCoverage> {
+        let mut coverage: Vec<BlockCoverage> = Vec::new();
+
+        for (codepoint, _) in self.chars.iter() {
+            // Characters are visited in order, so each block's are together
+            if let Some(last) = coverage.last_mut() {
+                if last.block.contains(codepoint) {
+                    last.covered += 1;
+                    continue;
+                }
+            }
+
+            if let Some(block) = blocks::block_of(codepoint) {
+                coverage.push(BlockCoverage { block, covered: 1 });
+            }
+        }
+
+        coverage
+    }
+
+    /// Returns the font's name, version and licence, as far as they're known.
+    /// They are read from BDF fonts' properties, and are set for the embedded
+    /// Unifont, but are otherwise unknown unless set with `set_info`.
+    pub fn info(&self) -> &FontInfo {
+        &self.info
+    }
+
+    /// Sets the font's name, version and licence.
+    pub fn set_info(&mut self, info: FontInfo) {
+        self.info = info;
+    }
+
+    /// Returns the underlying character definitions.
+    pub fn chars(&self) -> &FontChars {
+        &self.chars
+    }
+}
+
+impl Default for Font {
+    fn default() -> Font {
+        Font::new()
+    }
+}
+
+/// Parses each line of a .hex font, inserting the characters it defines into
+/// the supplied table. Blank lines are ignored.
+fn parse_hex<B: BufRead>(
+    chars: &mut FontChars,
+    reader: B,
+) -> Result<(), Error> {
+    let height = chars.height() as usize;
+    let mut bitmap_bytes = Vec::new();
+
+    for (line_i, l) in reader.lines().enumerate() {
+        let line = l?;
+        let line = line.trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        // Returned as the error value if the current line is malformed
+        let malformed = |reason: &str| Error::MalformedHex {
+            line: line_i + 1,
+            reason: reason.to_string(),
+        };
+
+        let mut split = line.split(':');
+
+        // Parse code point of current character
+        let codepoint = match split.next() {
+            Some(cp) => match u32::from_str_radix(cp, 16) {
+                Ok(cp_num) if cp_num <= 0x10ffff => cp_num,
+                Ok(_) => return Err(malformed("code point out of range")),
+                Err(_) => return Err(malformed("invalid code point")),
+            },
+
+            None => return Err(malformed("missing code point")),
+        };
+
+        // Retrieve bitmap string
+        let bitmap = match split.next() {
+            Some(bmp) => bmp,
+            None => return Err(malformed("missing bitmap")),
+        };
+
+        if split.next().is_some() {
+            return Err(malformed("unexpected data after bitmap"));
+        }
+
+        // Determine the width of the current character, from 2 digits per 8
+        // pixels of each row
+        let row_digits = bitmap.len() / height;
+        let char_width = match row_digits {
+            2 | 4 | 6 | 8 if row_digits * height == bitmap.len() => {
+                row_digits * 4
+            }
+            _ => {
+                return Err(malformed(&format!(
+                    "bitmap is not {}, {}, {} or {} digits",
+                    height * 2,
+                    height * 4,
+                    height * 6,
+                    height * 8
+                )))
+            }
+        };
+
+        // Convert hex bit pattern to binary, two digits per byte
+        bitmap_bytes.clear();
+
+        for i in 0..bitmap.len() / 2 {
+            bitmap_bytes.push(
+                match bitmap
+                    .get((i * 2)..(i + 1) * 2)
+                    .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                {
+                    Some(bits) => bits,
+                    None => return Err(malformed("invalid bitmap digits")),
+                },
+            );
+        }
+
+        // We have all the information we need; insert the completed character
+        // into the table.
+        chars.insert(codepoint, Glyph::new(char_width as u8, &bitmap_bytes))?;
+    }
+
+    Ok(())
+}
+
+/// Writes each character in the supplied table as a line of a .hex font.
+fn write_hex<W: Write>(chars: &FontChars, writer: W) -> Result<(), Error> {
+    let mut writer = BufWriter::new(writer);
+
+    for (codepoint, glyph) in chars.iter() {
+        if codepoint <= 0xffff {
+            write!(writer, "{:04X}:", codepoint)?;
+        } else {
+            write!(writer, "{:06X}:", codepoint)?;
+        }
+
+        for byte in glyph.bitmap() {
+            write!(writer, "{:02X}", byte)?;
+        }
+
+        writeln!(writer)?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_data_after_bitmap() {
+        let line = "0041:0000000018242442427E424242420000:junk";
+
+        match Font::from_hex_str(line) {
+            Err(Error::MalformedHex { line: 1, reason }) => {
+                assert_eq!(reason, "unexpected data after bitmap")
+            }
+            _ => panic!("extra field accepted"),
+        }
+    }
+
+    /// Checks that reading one of the bundled .hex files and writing it back
+    /// out produces an identical file.
+    fn assert_round_trips(file: &str) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join(file);
+        let original = std::fs::read(&path).unwrap();
+
+        let font = Font::from_path(&path).unwrap();
+        let mut written = Vec::new();
+        font.write_hex(&mut written).unwrap();
+
+        assert!(written == original, "{} doesn't round-trip", file);
+    }
+
+    #[test]
+    fn round_trips_unifont() {
+        assert_round_trips("unifont-11.0.02.hex");
+    }
+
+    #[test]
+    fn round_trips_unifont_upper() {
+        assert_round_trips("unifont_upper-11.0.02.hex");
+    }
+}
@@ -0,0 +1,585 @@
+/// Compact storage of character definitions. Rather than storing every glyph
+/// as a separate value in a hashmap, the bitmaps of all of a font's glyphs are
+/// packed back-to-back into one buffer, and located through a two-level page
+/// table indexed by code point, so (in a 16px tall font) half-width glyphs
+/// only take up 16 bytes, full-width glyphs 32 bytes, and look-ups are just two
+/// array accesses.
+///
+/// With the whole of Unifont's planes 0 and 1 loaded (67,548 glyphs), this
+/// takes up about 2.3MiB, against 4.5MiB for a hashmap of fixed-size glyphs,
+/// and looking up every code point is about five times faster.
+use std::fmt;
+
+use error::Error;
+
+/// Number of code points covered by each page of a `FontChars` page table.
+const PAGE_SIZE: usize = 256;
+
+/// Number of rows of pixels in each glyph of Unifont, and of any other font
+/// whose height isn't specified.
+pub const DEFAULT_HEIGHT: u8 = 16;
+
+/// The widest glyph which can be stored, in pixels.
+pub const MAX_WIDTH: u8 = 32;
+
+/// The highest code point that can be stored in a `FontChars`.
+const MAX_CODEPOINT: u32 = 0x10ffff;
+
+/// 3x5 pixel hex digits, used to draw code points into `FontChar::hex_box`
+/// glyphs. Each row uses the low three bits, with the MSB as the leftmost
+/// pixel.
+const HEX_DIGITS: [[u8; 5]; 16] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b001, 0b001, 0b001],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+    [0b111, 0b101, 0b111, 0b101, 0b101],
+    [0b110, 0b101, 0b110, 0b101, 0b110],
+    [0b111, 0b100, 0b100, 0b100, 0b111],
+    [0b110, 0b101, 0b101, 0b101, 0b110],
+    [0b111, 0b100, 0b111, 0b100, 0b111],
+    [0b111, 0b100, 0b111, 0b100, 0b100],
+];
+
+/// A borrowed view of a single character's definition, as returned by font
+/// look-ups.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Glyph<'a> {
+    /// Width, up to `MAX_WIDTH`. Most Unifont characters are either 8px
+    /// (half-width) or 16px (full-width).
+    width: u8,
+    /// Number of rows of pixels, which is the same for every glyph in a font
+    height: u8,
+    /// Rows of the character's form, each `width / 8` bytes long (rounded up),
+    /// with the MSB of the first byte as the leftmost pixel
+    bitmap: &'a [u8],
+}
+
+impl<'a> Glyph<'a> {
+    /// Creates a view of a character from its width (up to `MAX_WIDTH`), and
+    /// its rows of pixels, each `width / 8` bytes long (rounded up), with the
+    /// MSB of the first byte as the leftmost pixel. The height of the character
+    /// is the number of rows supplied.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the width is 0 or above `MAX_WIDTH`, or the bitmap doesn't
+    /// contain between 1 and 255 whole rows. Use `try_new` to check untrusted
+    /// bitmaps instead.
+    pub fn new(width: u8, bitmap: &'a [u8]) -> Glyph<'a> {
+        match Glyph::try_new(width, bitmap) {
+            Ok(glyph) => glyph,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Creates a view of a character in the same way as `new`, but returns
+    /// `Error::InvalidBitmap` if the width is 0 or above `MAX_WIDTH`, or the
+    /// bitmap doesn't contain between 1 and 255 whole rows.
+    pub fn try_new(width: u8, bitmap: &'a [u8]) -> Result<Glyph<'a>, Error> {
+        let invalid = Error::InvalidBitmap {
+            width: width as u32,
+            len: bitmap.len(),
+        };
+
+        if width == 0 || width > MAX_WIDTH {
+            return Err(invalid);
+        }
+
+        let row_bytes = row_bytes(width);
+        let height = bitmap.len() / row_bytes;
+        if height * row_bytes != bitmap.len() || height == 0 || height > 255 {
+            return Err(invalid);
+        }
+
+        Ok(Glyph {
+            width,
+            height: height as u8,
+            bitmap,
+        })
+    }
+
+    /// Returns the width of the character in pixels.
+    pub fn width(&self) -> u8 {
+        self.width
+    }
+
+    /// Returns the height of the character in pixels.
+    pub fn height(&self) -> u8 {
+        self.height
+    }
+
+    /// Returns the raw rows of the character's form.
+    pub fn bitmap(&self) -> &'a [u8] {
+        self.bitmap
+    }
+
+    /// Returns whether the pixel at (`x`, `y`) is set, where (0, 0) is the
+    /// top-left corner of the character.
+    pub fn pixel(&self, x: u32, y: u32) -> bool {
+        let row_bytes = row_bytes(self.width);
+        let byte = self.bitmap[y as usize * row_bytes + x as usize / 8];
+
+        byte & (0x80 >> (x % 8)) != 0
+    }
+
+    /// Copies the character's definition out of the font.
+    pub fn to_font_char(&self) -> FontChar {
+        FontChar {
+            width: self.width,
+            bitmap: self.bitmap.to_vec(),
+        }
+    }
+}
+
+impl<'a> fmt::Debug for Glyph<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Glyph")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .finish()
+    }
+}
+
+/// An owned character definition, which can be inserted into a `FontChars`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FontChar {
+    /// Width, up to `MAX_WIDTH`
+    width: u8,
+    /// Rows of the character's form, laid out as in `Glyph`
+    bitmap: Vec<u8>,
+}
+
+impl FontChar {
+    /// Creates a character from its width (up to `MAX_WIDTH`), and its rows of
+    /// pixels, each `width / 8` bytes long (rounded up), with the MSB of the
+    /// first byte as the leftmost pixel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the width is 0 or above `MAX_WIDTH`, or the bitmap doesn't
+    /// contain between 1 and 255 whole rows.
+    pub fn new(width: u8, bitmap: Vec<u8>) -> FontChar {
+        Glyph::new(width, &bitmap);
+
+        FontChar { width, bitmap }
+    }
+
+    /// Synthesises a full-width glyph for a character which isn't in a font,
+    /// following Unifont's convention: a box containing the code point in hex,
+    /// as two rows of two digits (or three, above U+FFFF).
+    pub fn hex_box(codepoint: u32) -> FontChar {
+        FontChar::hex_box_sized(codepoint, DEFAULT_HEIGHT)
+    }
+
+    /// Synthesises a `hex_box` glyph to fit a font of the given height. The
+    /// digits are centred vertically, and are left out if the font is less
+    /// than 13px tall, since they wouldn't fit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the height is 0.
+    pub fn hex_box_sized(codepoint: u32, height: u8) -> FontChar {
+        assert!(height > 0, "glyphs must be at least 1px tall");
+
+        let height = height as usize;
+        let mut rows = vec![0x8001u16; height];
+        rows[0] = 0xffff;
+        rows[height - 1] = 0xffff;
+
+        // Left-most pixel column of each digit in a row
+        let (digits, columns): (usize, &[usize]) = if codepoint > 0xffff {
+            (6, &[2, 6, 10])
+        } else {
+            (4, &[4, 9])
+        };
+        let per_row = digits / 2;
+
+        // Two rows of 5px tall digits, with a 1px gap, and a 1px margin below
+        let first_top = height.saturating_sub(10) / 2;
+
+        for i in 0..digits {
+            if height < 13 {
+                break;
+            }
+
+            let digit = (codepoint >> (4 * (digits - 1 - i))) & 0xf;
+            let top = if i < per_row {
+                first_top
+            } else {
+                first_top + 6
+            };
+            let shift = 13 - columns[i % per_row];
+
+            for (r, bits) in HEX_DIGITS[digit as usize].iter().enumerate() {
+                rows[top + r] |= (*bits as u16) << shift;
+            }
+        }
+
+        FontChar {
+            width: 16,
+            bitmap: rows.iter().flat_map(|row| row.to_be_bytes()).collect(),
+        }
+    }
+
+    /// Returns the width of the character in pixels.
+    pub fn width(&self) -> u8 {
+        self.width
+    }
+
+    /// Returns a view of the character, as would be returned by a look-up.
+    pub fn glyph(&self) -> Glyph<'_> {
+        Glyph {
+            width: self.width,
+            height: (self.bitmap.len() / row_bytes(self.width)) as u8,
+            bitmap: &self.bitmap,
+        }
+    }
+}
+
+/// One page of a `FontChars` page table, covering `PAGE_SIZE` consecutive code
+/// points.
+#[derive(Clone)]
+struct Page {
+    /// Byte offset of each character's bitmap within `FontChars::bitmaps`
+    offsets: [u32; PAGE_SIZE],
+    /// Width of each character, or 0 if the character isn't defined
+    widths: [u8; PAGE_SIZE],
+}
+
+/// The container type which relates Unicode code points with font character
+/// definitions. Every character in the table has the same height.
+#[derive(Clone)]
+pub struct FontChars {
+    /// Number of rows of pixels in each character
+    height: u8,
+    /// Pages of the table, indexed by `codepoint / PAGE_SIZE`. Pages which
+    /// don't contain any characters aren't allocated.
+    pages: Vec<Option<Box<Page>>>,
+    /// The bitmaps of every character, stored back-to-back
+    bitmaps: Vec<u8>,
+    /// Number of bytes of `bitmaps` left behind by replaced characters, which
+    /// are reclaimed by `compact` once they make up half of `bitmaps`
+    unused: usize,
+    /// Number of characters defined
+    len: usize,
+}
+
+impl FontChars {
+    /// Creates an empty table of 16px tall characters.
+    pub fn new() -> FontChars {
+        FontChars::with_height(DEFAULT_HEIGHT)
+    }
+
+    /// Creates an empty table of characters of the given height.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the height is 0.
+    pub fn with_height(height: u8) -> FontChars {
+        assert!(height > 0, "glyphs must be at least 1px tall");
+
+        FontChars {
+            height,
+            pages: Vec::new(),
+            bitmaps: Vec::new(),
+            unused: 0,
+            len: 0,
+        }
+    }
+
+    /// Returns the height of every character in the table, in pixels.
+    pub fn height(&self) -> u8 {
+        self.height
+    }
+
+    /// Looks up the definition of a single character.
+    pub fn get(&self, codepoint: u32) -> Option<Glyph<'_>> {
+        let page = match self.pages.get(codepoint as usize / PAGE_SIZE) {
+            Some(Some(page)) => page,
+            _ => return None,
+        };
+
+        let i = codepoint as usize % PAGE_SIZE;
+        let width = page.widths[i];
+        if width == 0 {
+            return None;
+        }
+
+        let offset = page.offsets[i] as usize;
+        let len = row_bytes(width) * self.height as usize;
+
+        Some(Glyph {
+            width,
+            height: self.height,
+            bitmap: &self.bitmaps[offset..offset + len],
+        })
+    }
+
+    /// Returns whether a character is defined.
+    pub fn contains(&self, codepoint: u32) -> bool {
+        self.get(codepoint).is_some()
+    }
+
+    /// Defines a character, replacing any existing definition of it. The space
+    /// taken up by a replaced definition is reused, so characters can be
+    /// redefined any number of times without the table growing. Returns
+    /// `Error::InvalidGlyph` if the code point is above U+10FFFF, or the
+    /// character isn't the same height as the table.
+    pub fn insert(
+        &mut self,
+        codepoint: u32,
+        glyph: Glyph,
+    ) -> Result<(), Error> {
+        if codepoint > MAX_CODEPOINT || glyph.height != self.height {
+            return Err(Error::InvalidGlyph {
+                codepoint,
+                width: glyph.width as u32,
+                height: glyph.height as u32,
+            });
+        }
+
+        let page_i = codepoint as usize / PAGE_SIZE;
+        if self.pages.len() <= page_i {
+            self.pages.resize(page_i + 1, None);
+        }
+
+        let page = self.pages[page_i].get_or_insert_with(|| {
+            Box::new(Page {
+                offsets: [0; PAGE_SIZE],
+                widths: [0; PAGE_SIZE],
+            })
+        });
+
+        let i = codepoint as usize % PAGE_SIZE;
+        let old_width = page.widths[i];
+        let old_len = row_bytes(old_width) * self.height as usize;
+        let new_len = glyph.bitmap.len();
+
+        if old_width == 0 {
+            self.len += 1;
+        }
+
+        if old_width != 0 && new_len <= old_len {
+            // Overwrite the old bitmap in place, since the new one fits
+            let offset = page.offsets[i] as usize;
+            self.bitmaps[offset..offset + new_len]
+                .copy_from_slice(glyph.bitmap);
+            self.unused += old_len - new_len;
+        } else {
+            page.offsets[i] = self.bitmaps.len() as u32;
+            self.bitmaps.extend_from_slice(glyph.bitmap);
+            self.unused += old_len;
+        }
+        page.widths[i] = glyph.width;
+
+        if self.unused > self.bitmaps.len() / 2 {
+            self.compact();
+        }
+
+        Ok(())
+    }
+
+    /// Copies every character's bitmap into a new buffer without any of the
+    /// space left behind by replaced characters.
+    fn compact(&mut self) {
+        let mut bitmaps = Vec::with_capacity(self.bitmaps.len() - self.unused);
+
+        for page in self.pages.iter_mut().flatten() {
+            for i in 0..PAGE_SIZE {
+                if page.widths[i] == 0 {
+                    continue;
+                }
+
+                let offset = page.offsets[i] as usize;
+                let len = row_bytes(page.widths[i]) * self.height as usize;
+
+                page.offsets[i] = bitmaps.len() as u32;
+                bitmaps.extend_from_slice(&self.bitmaps[offset..offset + len]);
+            }
+        }
+
+        self.bitmaps = bitmaps;
+        self.unused = 0;
+    }
+
+    /// Returns the number of characters defined.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether no characters are defined.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns an iterator over each defined character's code point and
+    /// definition, in code point order.
+    pub fn iter(&self) -> impl Iterator<Item = (u32, Glyph<'_>)> {
+        (0..(self.pages.len() * PAGE_SIZE) as u32)
+            .filter_map(move |cp| self.get(cp).map(|glyph| (cp, glyph)))
+    }
+}
+
+impl Default for FontChars {
+    fn default() -> FontChars {
+        FontChars::new()
+    }
+}
+
+/// Returns the number of bytes in each row of a glyph of the given width.
+fn row_bytes(width: u8) -> usize {
+    (width as usize).div_ceil(8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the pixels of a glyph as strings of `#` and `.`, one per row.
+    fn pixels(glyph: Glyph) -> Vec<String> {
+        (0..glyph.height() as u32)
+            .map(|y| {
+                (0..glyph.width() as u32)
+                    .map(|x| if glyph.pixel(x, y) { '#' } else { '.' })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn hex_box_shows_four_digits() {
+        let hex_box = FontChar::hex_box(0x20ac);
+
+        assert_eq!(
+            pixels(hex_box.glyph()),
+            [
+                "################",
+                "#..............#",
+                "#..............#",
+                "#...###..###...#",
+                "#.....#..#.#...#",
+                "#...###..#.#...#",
+                "#...#....#.#...#",
+                "#...###..###...#",
+                "#..............#",
+                "#...###..###...#",
+                "#...#.#..#.....#",
+                "#...###..#.....#",
+                "#...#.#..#.....#",
+                "#...#.#..###...#",
+                "#..............#",
+                "################",
+            ]
+        );
+    }
+
+    #[test]
+    fn hex_box_shows_six_digits() {
+        let hex_box = FontChar::hex_box(0x1f600);
+
+        assert_eq!(
+            pixels(hex_box.glyph()),
+            [
+                "################",
+                "#..............#",
+                "#..............#",
+                "#.###..#..###..#",
+                "#.#.#.##..#....#",
+                "#.#.#..#..###..#",
+                "#.#.#..#..#....#",
+                "#.###.###.#....#",
+                "#..............#",
+                "#.###.###.###..#",
+                "#.#...#.#.#.#..#",
+                "#.###.#.#.#.#..#",
+                "#.#.#.#.#.#.#..#",
+                "#.###.###.###..#",
+                "#..............#",
+                "################",
+            ]
+        );
+    }
+
+    #[test]
+    fn hex_box_fits_height() {
+        // Too short for the digits, so only the box is drawn
+        let small = FontChar::hex_box_sized(0x41, 8);
+        assert_eq!(small.glyph().height(), 8);
+        assert!(!small.glyph().pixel(5, 3));
+
+        let tall = FontChar::hex_box_sized(0x41, 20);
+        assert_eq!(pixels(tall.glyph())[5], "#...###..###...#");
+    }
+
+    #[test]
+    fn insert_and_get() {
+        let mut chars = FontChars::new();
+        chars.insert(0x41, Glyph::new(8, &[0x18; 16])).unwrap();
+        chars.insert(0x3042, Glyph::new(16, &[0xff; 32])).unwrap();
+
+        assert_eq!(chars.len(), 2);
+        assert_eq!(chars.get(0x41).unwrap().bitmap(), &[0x18; 16]);
+        assert_eq!(chars.get(0x3042).unwrap().width(), 16);
+        assert!(chars.get(0x42).is_none());
+        assert!(chars.get(0x10ffff).is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_glyphs() {
+        let invalid_bitmap = |width, len| {
+            matches!(
+                Glyph::try_new(width, &[0; 64][..len]),
+                Err(Error::InvalidBitmap { .. })
+            )
+        };
+
+        assert!(invalid_bitmap(0, 16));
+        assert!(invalid_bitmap(33, 64));
+        assert!(invalid_bitmap(16, 0));
+        assert!(invalid_bitmap(16, 31));
+        assert!(Glyph::try_new(16, &[0; 32]).is_ok());
+
+        let mut chars = FontChars::new();
+        let glyph = Glyph::new(8, &[0; 16]);
+        let short = Glyph::new(8, &[0; 8]);
+
+        assert!(chars.insert(0x110000, glyph).is_err());
+        assert!(chars.insert(0x41, short).is_err());
+        assert_eq!(chars.len(), 0);
+    }
+
+    #[test]
+    fn replacing_reuses_space() {
+        let mut chars = FontChars::new();
+        chars.insert(0x20, Glyph::new(8, &[0; 16])).unwrap();
+
+        // Alternate between widths, which can't be overwritten in place every
+        // time
+        for i in 0..1000 {
+            let (width, bitmap) = match i % 3 {
+                0 => (16, [i as u8; 64]),
+                1 => (8, [i as u8; 64]),
+                _ => (32, [i as u8; 64]),
+            };
+            let len = row_bytes(width) * 16;
+            chars
+                .insert(0x41, Glyph::new(width, &bitmap[..len]))
+                .unwrap();
+
+            let glyph = chars.get(0x41).unwrap();
+            assert_eq!(glyph.width(), width);
+            assert_eq!(glyph.bitmap(), &bitmap[..len]);
+            assert_eq!(chars.get(0x20).unwrap().bitmap(), &[0; 16]);
+        }
+
+        assert_eq!(chars.len(), 2);
+        assert!(chars.bitmaps.len() <= 2 * (16 + 64));
+    }
+}
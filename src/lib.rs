@@ -1,10 +1,36 @@
-// For unifont.rs
+// For font.rs
+extern crate flate2;
 extern crate lzma;
 
 // For renderer.rs
-extern crate bit_field;
 extern crate sdl2;
 
+/// Parsing of BDF fonts
+mod bdf;
+
+/// Names and ranges of the Unicode blocks, for reporting fonts' coverage
+pub mod blocks;
+
+/// The error type returned by font loading and rendering
+pub mod error;
+
+/// The code point ranges selected by each of the cargo features; shared with
+/// build.rs
+mod features;
+
+/// Loading of .hex, BDF and PSF fonts other than the embedded Unifont
+pub mod font;
+
+/// Compact storage of the character definitions which make up a font
+pub mod glyph;
+
+/// Reading and writing of PC Screen Font (PSF) console fonts
+mod psf;
+
+/// Finds where lines may be broken when wrapping text; a small subset of the
+/// Unicode line breaking algorithm (UAX #14)
+mod linebreak;
+
 /// Manages Unifont initialisation and rendering; the main API interface
 pub mod renderer;
 
@@ -0,0 +1,111 @@
+/// Returns whether a line may be broken between the two supplied characters,
+/// i.e. whether `after` may start a new line.
+///
+/// Breaks are allowed after spaces and hyphens, and on either side of CJK
+/// characters, except where that would leave punctuation at the start or end of
+/// a line. This is enough for the kinds of text that Unifont is generally used
+/// for, without needing the full Unicode line breaking tables.
+pub fn is_break_opportunity(before: char, after: char) -> bool {
+    // Spaces are left hanging at the end of a line, rather than starting one
+    if after.is_whitespace() {
+        return false;
+    }
+    if before.is_whitespace() {
+        return true;
+    }
+
+    if is_opening_punctuation(before) || is_closing_punctuation(after) {
+        return false;
+    }
+
+    if is_hyphen(before) {
+        // Keep negative numbers together
+        return !after.is_numeric() && !is_hyphen(after);
+    }
+
+    is_ideographic(before) || is_ideographic(after)
+}
+
+/// Hyphens, after which a word may be broken.
+fn is_hyphen(c: char) -> bool {
+    matches!(c, '-' | '\u{2010}' | '\u{2012}' | '\u{2013}')
+}
+
+/// Characters which may be broken before or after, without needing spaces
+/// between them: CJK ideographs, kana and Hangul syllables, and full-width
+/// forms.
+fn is_ideographic(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x2e80..=0x2fff
+            | 0x3000..=0x303f
+            | 0x3040..=0x309f
+            | 0x30a0..=0x30ff
+            | 0x3100..=0x31ff
+            | 0x3400..=0x4dbf
+            | 0x4e00..=0x9fff
+            | 0xa000..=0xa4cf
+            | 0xac00..=0xd7af
+            | 0xf900..=0xfaff
+            | 0xff00..=0xffef
+            | 0x20000..=0x3ffff
+    )
+}
+
+/// Punctuation which must not start a line, including the small kana and
+/// iteration marks forbidden by Japanese line breaking rules (kinsoku).
+fn is_closing_punctuation(c: char) -> bool {
+    concat!(
+        ",.:;!?)]}%",
+        "、。，．・：；？！）」』】〕〉》｝〟",
+        "ーゝゞ々ヽヾ",
+        "ぁぃぅぇぉっゃゅょァィゥェォッャュョ",
+    )
+    .contains(c)
+}
+
+/// Punctuation which must not end a line.
+fn is_opening_punctuation(c: char) -> bool {
+    "([{（「『【〔〈《｛〝".contains(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn breaks_after_spaces() {
+        assert!(is_break_opportunity(' ', 'a'));
+        assert!(!is_break_opportunity('a', ' '));
+        assert!(!is_break_opportunity('a', 'b'));
+    }
+
+    #[test]
+    fn breaks_after_hyphens() {
+        assert!(is_break_opportunity('-', 'a'));
+        assert!(!is_break_opportunity('a', '-'));
+        // Negative numbers and runs of hyphens stay together
+        assert!(!is_break_opportunity('-', '1'));
+        assert!(!is_break_opportunity('-', '-'));
+    }
+
+    #[test]
+    fn breaks_between_ideographs() {
+        assert!(is_break_opportunity('日', '本'));
+        assert!(is_break_opportunity('a', 'あ'));
+        assert!(is_break_opportunity('한', 'a'));
+    }
+
+    #[test]
+    fn keeps_punctuation_with_its_text() {
+        // Kinsoku: closing punctuation and small kana can't start a line
+        assert!(!is_break_opportunity('本', '。'));
+        assert!(!is_break_opportunity('本', '」'));
+        assert!(!is_break_opportunity('ジ', 'ャ'));
+        assert!(!is_break_opportunity('ラ', 'ー'));
+        // Opening punctuation can't end a line
+        assert!(!is_break_opportunity('「', '本'));
+        assert!(!is_break_opportunity('(', 'a'));
+        assert!(is_break_opportunity('本', '「'));
+    }
+}
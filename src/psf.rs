@@ -0,0 +1,439 @@
+/// Reading and writing of the PC Screen Font (PSF) format used for Linux
+/// console fonts, in both its original (PSF1) and extended (PSF2) versions.
+///
+/// A PSF font is a header, followed by a list of equally sized glyphs, and
+/// optionally a table which maps each glyph to the Unicode code points that it
+/// is used to display. Fonts without a table are assumed to map each glyph to
+/// the code point equal to its index.
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+use flate2::read::GzDecoder;
+
+use error::Error;
+use font::Font;
+use glyph::{Glyph, MAX_WIDTH};
+
+/// Magic bytes at the start of a PSF1 font.
+const PSF1_MAGIC: [u8; 2] = [0x36, 0x04];
+
+/// Magic bytes at the start of a PSF2 font.
+const PSF2_MAGIC: [u8; 4] = [0x72, 0xb5, 0x4a, 0x86];
+
+/// Magic bytes at the start of a gzip stream, used to detect .psf.gz fonts.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// PSF1 mode flag: the font contains 512 glyphs, rather than 256.
+const PSF1_MODE512: u8 = 0x01;
+
+/// PSF1 mode flags which indicate that the font has a Unicode table.
+const PSF1_MODEHASTAB: u8 = 0x02 | 0x04;
+
+/// PSF1 Unicode table entry which ends each glyph's list of code points.
+const PSF1_SEPARATOR: u16 = 0xffff;
+
+/// PSF1 Unicode table entry which starts a sequence of code points.
+const PSF1_STARTSEQ: u16 = 0xfffe;
+
+/// PSF2 header flag: the font has a Unicode table.
+const PSF2_HAS_UNICODE_TABLE: u32 = 0x01;
+
+/// PSF2 Unicode table byte which ends each glyph's list of code points.
+const PSF2_SEPARATOR: u8 = 0xff;
+
+/// PSF2 Unicode table byte which starts a sequence of code points.
+const PSF2_STARTSEQ: u8 = 0xfe;
+
+/// Size of the header written by `write_psf2`.
+const PSF2_HEADER_SIZE: u32 = 32;
+
+/// Reads a complete PSF1 or PSF2 font, which may be gzipped.
+pub fn read_psf<R: Read>(mut reader: R) -> Result<Font, Error> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+
+    if data.starts_with(&GZIP_MAGIC) {
+        let mut decompressed = Vec::new();
+        GzDecoder::new(data.as_slice()).read_to_end(&mut decompressed)?;
+        data = decompressed;
+    }
+
+    if data.starts_with(&PSF1_MAGIC) {
+        parse_psf1(&data)
+    } else if data.starts_with(&PSF2_MAGIC) {
+        parse_psf2(&data)
+    } else {
+        Err(malformed("not a PSF font"))
+    }
+}
+
+/// Writes a font in the PSF2 format, with a Unicode table. Every glyph is
+/// padded to the width of the font's widest character, and characters which
+/// look the same share a glyph.
+pub fn write_psf2<W: Write>(font: &Font, mut writer: W) -> Result<(), Error> {
+    let width = font
+        .chars()
+        .iter()
+        .map(|(_, glyph)| glyph.width())
+        .max()
+        .unwrap_or(8);
+    let row_bytes = (width as usize).div_ceil(8);
+    let height = font.height() as usize;
+
+    // Each distinct glyph, and the code points which are drawn with it
+    let mut glyphs: Vec<u8> = Vec::new();
+    let mut table: Vec<Vec<u32>> = Vec::new();
+    let mut indices: HashMap<Vec<u8>, usize> = HashMap::new();
+
+    for (codepoint, glyph) in font.chars().iter() {
+        let glyph_row_bytes = (glyph.width() as usize).div_ceil(8);
+
+        let mut cell = vec![0; row_bytes * height];
+        for (row, bytes) in glyph.bitmap().chunks(glyph_row_bytes).enumerate() {
+            let start = row * row_bytes;
+            cell[start..start + bytes.len()].copy_from_slice(bytes);
+        }
+
+        let index = *indices.entry(cell).or_insert_with_key(|cell| {
+            glyphs.extend_from_slice(cell);
+            table.push(Vec::new());
+            table.len() - 1
+        });
+        table[index].push(codepoint);
+    }
+
+    let header = [
+        0,
+        PSF2_HEADER_SIZE,
+        PSF2_HAS_UNICODE_TABLE,
+        table.len() as u32,
+        (row_bytes * height) as u32,
+        height as u32,
+        width as u32,
+    ];
+
+    writer.write_all(&PSF2_MAGIC)?;
+    for field in header.iter() {
+        writer.write_all(&field.to_le_bytes())?;
+    }
+    writer.write_all(&glyphs)?;
+
+    let mut utf8 = [0; 4];
+    for codepoints in table {
+        // Surrogates can't be encoded as UTF-8, so can't be written
+        for c in codepoints.into_iter().filter_map(char::from_u32) {
+            writer.write_all(c.encode_utf8(&mut utf8).as_bytes())?;
+        }
+        writer.write_all(&[PSF2_SEPARATOR])?;
+    }
+
+    Ok(())
+}
+
+/// Parses a PSF1 font, whose glyphs are always 8px wide.
+fn parse_psf1(data: &[u8]) -> Result<Font, Error> {
+    if data.len() < 4 {
+        return Err(malformed("header is truncated"));
+    }
+
+    let mode = data[2];
+    let height = data[3] as usize;
+    let length = if mode & PSF1_MODE512 != 0 { 512 } else { 256 };
+
+    let glyphs = match data.get(4..4 + length * height) {
+        Some(glyphs) if height > 0 => glyphs,
+        Some(_) => return Err(malformed("glyphs are 0px tall")),
+        None => return Err(malformed("glyphs are truncated")),
+    };
+
+    // Each glyph's code points, as a list of 16-bit values
+    let mut mappings: Vec<Vec<u32>> = Vec::with_capacity(length);
+
+    if mode & PSF1_MODEHASTAB != 0 {
+        let mut entries = data[4 + glyphs.len()..]
+            .chunks_exact(2)
+            .map(|entry| u16::from_le_bytes([entry[0], entry[1]]));
+
+        for _ in 0..length {
+            let mut codepoints = Vec::new();
+            let mut in_sequence = false;
+
+            loop {
+                match entries.next() {
+                    Some(PSF1_SEPARATOR) => break,
+                    Some(PSF1_STARTSEQ) => in_sequence = true,
+                    // Sequences of combined code points can't be represented
+                    Some(_) if in_sequence => {}
+                    Some(cp) => codepoints.push(cp as u32),
+                    None => {
+                        return Err(malformed("Unicode table is truncated"))
+                    }
+                }
+            }
+
+            mappings.push(codepoints);
+        }
+    } else {
+        mappings.extend((0..length as u32).map(|i| vec![i]));
+    }
+
+    let mut font = Font::with_height(height as u8);
+    for (bitmap, codepoints) in glyphs.chunks(height).zip(mappings) {
+        for codepoint in codepoints {
+            font.insert(codepoint, Glyph::new(8, bitmap))?;
+        }
+    }
+
+    Ok(font)
+}
+
+/// Parses a PSF2 font.
+fn parse_psf2(data: &[u8]) -> Result<Font, Error> {
+    // The header is made up of 32-bit fields, after the magic bytes
+    let field = |i: usize| match data.get(4 + i * 4..8 + i * 4) {
+        Some(b) => Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize),
+        None => Err(malformed("header is truncated")),
+    };
+
+    let header_size = field(1)?;
+    let flags = field(2)? as u32;
+    let length = field(3)?;
+    let char_size = field(4)?;
+    let height = field(5)?;
+    let width = field(6)?;
+
+    if width == 0 || width > MAX_WIDTH as usize {
+        return Err(malformed("glyphs must be between 1 and 32px wide"));
+    }
+    if height == 0 || height > 255 {
+        return Err(malformed("glyphs must be between 1 and 255px tall"));
+    }
+    if char_size != width.div_ceil(8) * height {
+        return Err(malformed("glyph size doesn't match width and height"));
+    }
+
+    let glyphs = match length
+        .checked_mul(char_size)
+        .and_then(|size| data.get(header_size..header_size.checked_add(size)?))
+    {
+        Some(glyphs) => glyphs,
+        None => return Err(malformed("glyphs are truncated")),
+    };
+
+    // Each glyph's code points
+    let mut mappings: Vec<Vec<u32>> = Vec::with_capacity(length);
+
+    if flags & PSF2_HAS_UNICODE_TABLE != 0 {
+        let table = &data[header_size + glyphs.len()..];
+
+        // The separator never appears within UTF-8, so every glyph's entry
+        // has been read once this many have been found
+        let separators = table.iter().filter(|&&b| b == PSF2_SEPARATOR);
+        if separators.count() < length {
+            return Err(malformed("Unicode table is truncated"));
+        }
+
+        for entry in table.split(|&b| b == PSF2_SEPARATOR).take(length) {
+            // Sequences of combined code points can't be represented, so only
+            // the single code points before the first sequence are used
+            let singles = match entry.iter().position(|&b| b == PSF2_STARTSEQ) {
+                Some(end) => &entry[..end],
+                None => entry,
+            };
+
+            match ::std::str::from_utf8(singles) {
+                Ok(s) => mappings.push(s.chars().map(|c| c as u32).collect()),
+                Err(_) => return Err(malformed("Unicode table is not UTF-8")),
+            }
+        }
+    } else {
+        mappings.extend((0..length as u32).map(|i| vec![i]));
+    }
+
+    let mut font = Font::with_height(height as u8);
+    for (bitmap, codepoints) in glyphs.chunks(char_size).zip(mappings) {
+        for codepoint in codepoints {
+            font.insert(codepoint, Glyph::new(width as u8, bitmap))?;
+        }
+    }
+
+    Ok(font)
+}
+
+/// Returns an error describing why a PSF font is malformed.
+fn malformed(reason: &str) -> Error {
+    Error::MalformedPsf(reason.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    /// Returns a PSF1 font of 256 2px tall glyphs, where each glyph's rows are
+    /// its index, followed by the supplied Unicode table.
+    fn psf1(table: &[u16]) -> Vec<u8> {
+        let mode = if table.is_empty() { 0 } else { 0x02 };
+        let mut data = vec![PSF1_MAGIC[0], PSF1_MAGIC[1], mode, 2];
+
+        for i in 0..256 {
+            data.extend_from_slice(&[i as u8, i as u8]);
+        }
+        for entry in table {
+            data.extend_from_slice(&entry.to_le_bytes());
+        }
+
+        data
+    }
+
+    /// Returns a PSF2 font of three 10x2 glyphs, where each glyph's rows are
+    /// its index, followed by the supplied Unicode table.
+    fn psf2(table: &[u8]) -> Vec<u8> {
+        let flags = if table.is_empty() { 0 } else { 1 };
+        let header = [0, PSF2_HEADER_SIZE, flags, 3, 4, 2, 10];
+
+        let mut data = PSF2_MAGIC.to_vec();
+        for field in header.iter() {
+            data.extend_from_slice(&field.to_le_bytes());
+        }
+        for i in 0..3 {
+            data.extend_from_slice(&[i, 0, i, 0]);
+        }
+        data.extend_from_slice(table);
+
+        data
+    }
+
+    #[test]
+    fn reads_psf1_unicode_table() {
+        // Glyph 0 is 'A', and 'A' followed by a combining accent, which is
+        // skipped. Glyph 1 is 'B' and Greek capital beta.
+        let mut table = vec![0x41, PSF1_STARTSEQ, 0x41, 0x301, PSF1_SEPARATOR];
+        table.extend_from_slice(&[0x42, 0x392, PSF1_SEPARATOR]);
+        table.extend_from_slice(&[PSF1_SEPARATOR; 254]);
+
+        let font = read_psf(psf1(&table).as_slice()).unwrap();
+
+        assert_eq!(font.height(), 2);
+        assert_eq!(font.chars().len(), 3);
+        assert_eq!(font.get(0x41).unwrap().bitmap(), &[0, 0]);
+        assert_eq!(font.get(0x42).unwrap().bitmap(), &[1, 1]);
+        assert_eq!(font.get(0x392).unwrap().bitmap(), &[1, 1]);
+        assert!(font.get(0x301).is_none());
+    }
+
+    #[test]
+    fn reads_psf1_without_table() {
+        let font = read_psf(psf1(&[]).as_slice()).unwrap();
+
+        assert_eq!(font.chars().len(), 256);
+        assert_eq!(font.get(0xff).unwrap().bitmap(), &[0xff, 0xff]);
+    }
+
+    #[test]
+    fn rejects_truncated_psf1() {
+        let table = [PSF1_SEPARATOR; 255];
+        assert!(read_psf(psf1(&table).as_slice()).is_err());
+
+        let mut data = psf1(&[]);
+        data.truncate(100);
+        assert!(read_psf(data.as_slice()).is_err());
+        assert!(read_psf(&PSF1_MAGIC[..]).is_err());
+    }
+
+    #[test]
+    fn reads_psf2_unicode_table() {
+        let mut table = "A".as_bytes().to_vec();
+        table.push(PSF2_STARTSEQ);
+        table.extend_from_slice("A\u{301}".as_bytes());
+        table.push(PSF2_SEPARATOR);
+        table.extend_from_slice("Bβ".as_bytes());
+        table.push(PSF2_SEPARATOR);
+        table.extend_from_slice("€".as_bytes());
+        table.push(PSF2_SEPARATOR);
+
+        let font = read_psf(psf2(&table).as_slice()).unwrap();
+
+        assert_eq!(font.height(), 2);
+        assert_eq!(font.chars().len(), 4);
+        assert_eq!(font.get(0x41).unwrap().bitmap(), &[0, 0, 0, 0]);
+        assert_eq!(font.get('β' as u32).unwrap().bitmap(), &[1, 0, 1, 0]);
+        assert_eq!(font.get('€' as u32).unwrap().width(), 10);
+        assert!(font.get(0x301).is_none());
+    }
+
+    #[test]
+    fn reads_psf2_without_table() {
+        let font = read_psf(psf2(&[]).as_slice()).unwrap();
+
+        assert_eq!(font.chars().len(), 3);
+        assert_eq!(font.get(2).unwrap().bitmap(), &[2, 0, 2, 0]);
+    }
+
+    #[test]
+    fn rejects_truncated_psf2() {
+        // The last glyph's entry is missing its separator
+        let table = [b'A', PSF2_SEPARATOR, b'B', PSF2_SEPARATOR, b'C'];
+        assert!(read_psf(psf2(&table).as_slice()).is_err());
+
+        let mut data = psf2(&[]);
+        data.truncate(40);
+        assert!(read_psf(data.as_slice()).is_err());
+        data.truncate(20);
+        assert!(read_psf(data.as_slice()).is_err());
+    }
+
+    #[test]
+    fn reads_gzipped_fonts() {
+        let data = psf2(&[]);
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&data).unwrap();
+        let gzipped = encoder.finish().unwrap();
+
+        let font = read_psf(gzipped.as_slice()).unwrap();
+
+        assert_eq!(font.chars().len(), 3);
+        assert_eq!(font.get(1).unwrap().bitmap(), &[1, 0, 1, 0]);
+    }
+
+    #[test]
+    fn round_trips_psf2() {
+        let mut font = Font::with_height(4);
+        font.insert(0x41, Glyph::new(8, &[0x18, 0x24, 0x7e, 0x42]))
+            .unwrap();
+        font.insert(0x391, Glyph::new(8, &[0x18, 0x24, 0x7e, 0x42]))
+            .unwrap();
+        font.insert(
+            0x3042,
+            Glyph::new(12, &[0xff, 0xf0, 0, 0, 0x80, 0x10, 1, 2]),
+        )
+        .unwrap();
+        font.insert(0x1f600, Glyph::new(1, &[0x80, 0, 0x80, 0]))
+            .unwrap();
+
+        let mut data = Vec::new();
+        write_psf2(&font, &mut data).unwrap();
+        let read = read_psf(data.as_slice()).unwrap();
+
+        // Identical glyphs are shared
+        assert_eq!(
+            u32::from_le_bytes([data[16], data[17], data[18], data[19]]),
+            3
+        );
+
+        assert_eq!(read.chars().len(), font.chars().len());
+        for (codepoint, glyph) in font.chars().iter() {
+            let copy = read.get(codepoint).unwrap();
+            assert_eq!(copy.width(), 12);
+
+            for y in 0..4 {
+                for x in 0..12 {
+                    let set = x < glyph.width() as u32 && glyph.pixel(x, y);
+                    assert_eq!(copy.pixel(x, y), set);
+                }
+            }
+        }
+    }
+}
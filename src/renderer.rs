@@ -1,17 +1,96 @@
 use sdl2::pixels::Color;
 use sdl2::pixels::PixelFormatEnum;
 use sdl2::rect::Rect;
-use sdl2::surface::Surface;
-
-use bit_field::BitField;
+use sdl2::surface::{Surface, SurfaceRef};
 
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::ops::Range;
 use std::slice::IterMut;
+use std::sync::Arc;
 
+use error::Error;
+use font::{Font, FontChar, Glyph};
+use linebreak;
 use unifont;
 
-/// Number of vertical pixels in each Unifont character.
-const UNIFONT_HEIGHT: u32 = 16;
+/// Marks where text has been cut out by `SurfaceRenderer::truncate`, unless
+/// the renderer's `ellipsis` is changed.
+const DEFAULT_ELLIPSIS: &str = "\u{2026}";
+
+/// Decides what happens when text contains a character which the font does not
+/// define. Measuring and drawing always apply the same policy, so measured
+/// widths match drawn widths.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MissingGlyphPolicy {
+    /// Fail with `Error::MissingGlyph`. This is the default.
+    Error,
+    /// Leave the character out, as if it wasn't in the text.
+    Skip,
+    /// Draw another character in its place, such as U+FFFD REPLACEMENT
+    /// CHARACTER. Fails with `Error::MissingGlyph` if the replacement isn't in
+    /// the font either.
+    Replace(char),
+    /// Draw a full-width box containing the character's code point in tiny hex
+    /// digits, the same way Unifont itself shows characters it doesn't cover.
+    HexBox,
+}
+
+/// Decides where each line of text is placed horizontally, relative to the
+/// surface (or box) that it's drawn to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HorizontalAlign {
+    /// Lines start at the left edge. This is the default.
+    Left,
+    /// Lines are centred.
+    Center,
+    /// Lines end at the right edge.
+    Right,
+    /// Lines which were broken by wrapping are stretched to both edges, by
+    /// widening the spaces between words. The last line of each paragraph is
+    /// aligned to the left.
+    Justify,
+}
+
+/// Decides where the block of text is placed vertically, relative to the
+/// surface (or box) that it's drawn to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerticalAlign {
+    /// Text starts at the top edge. This is the default.
+    Top,
+    /// Text is centred.
+    Middle,
+    /// Text ends at the bottom edge.
+    Bottom,
+}
+
+/// Decides which part of a line `SurfaceRenderer::truncate` cuts characters
+/// from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Truncation {
+    /// Keep the end of the line, e.g. `…the line`.
+    Start,
+    /// Keep both ends of the line, e.g. `The l…line`, which suits file paths.
+    Middle,
+    /// Keep the start of the line, e.g. `The line…`.
+    End,
+}
+
+/// A character which none of a renderer's fonts define, as found by
+/// `SurfaceRenderer::check`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MissingChar {
+    /// The character itself
+    pub character: char,
+    /// Byte index of the character within the text
+    pub index: usize,
+    /// Index of the character within the text, counting in `char`s
+    pub char_index: usize,
+    /// The cargo feature which would embed the character in Unifont, if the
+    /// renderer draws with Unifont and Unifont's character selection is why
+    /// it's missing (see `unifont::feature_for`)
+    pub feature: Option<&'static str>,
+}
 
 /// Storage class for rendering settings.
 pub struct SurfaceRenderer {
@@ -32,6 +111,35 @@ pub struct SurfaceRenderer {
     /// Whether or not to make text italicised. Simply shifts pixels to the
     /// right by one additional pixel, every two vertical pixels.
     pub italic: bool,
+    /// Number of extra pixels to leave between each line of text. Not
+    /// affected by `scale`.
+    pub line_spacing: u32,
+    /// If set, lines which would be wider than this many pixels (including the
+    /// extra width taken up by bold and italic text) are wrapped onto the next
+    /// line, preferably between words.
+    pub wrap_width: Option<u32>,
+    /// How each line is aligned horizontally.
+    pub h_align: HorizontalAlign,
+    /// How the text is aligned vertically, when drawn with `draw_sized`.
+    pub v_align: VerticalAlign,
+    /// Inserted in place of the characters cut out by `truncate`. Defaults to
+    /// `…` (U+2026 HORIZONTAL ELLIPSIS).
+    pub ellipsis: String,
+
+    /// The font to draw text with. The embedded Unifont is used if `None`.
+    pub font: Option<Arc<Font>>,
+    /// Characters which take priority over those in `font`, such as icons
+    /// added to the Private Use Area, or replacements for existing characters.
+    /// Should be the same height as `font`.
+    pub overlay: Option<Arc<Font>>,
+    /// Fonts which are tried in order for characters which aren't in `font`,
+    /// before the missing glyph policy is applied. Characters from fonts of
+    /// different sizes are aligned on their baselines, and lines are made tall
+    /// enough to fit every font's characters. To fall back on the embedded
+    /// Unifont, add `unifont::get_unifont()`.
+    pub fallbacks: Vec<Arc<Font>>,
+    /// What to do with characters that aren't in the font.
+    pub missing_glyph: MissingGlyphPolicy,
 }
 
 impl SurfaceRenderer {
@@ -45,166 +153,539 @@ impl SurfaceRenderer {
             scale: 1,
             bold: false,
             italic: false,
+            line_spacing: 0,
+            wrap_width: None,
+            h_align: HorizontalAlign::Left,
+            v_align: VerticalAlign::Top,
+            ellipsis: DEFAULT_ELLIPSIS.to_string(),
+            font: None,
+            overlay: None,
+            fallbacks: Vec::new(),
+            missing_glyph: MissingGlyphPolicy::Error,
         }
     }
 
+    /// Creates a new renderer which draws text using the supplied font, rather
+    /// than the embedded Unifont.
+    pub fn with_font(
+        font: Arc<Font>,
+        fg_color: Color,
+        bg_color: Color,
+    ) -> SurfaceRenderer {
+        let mut renderer = SurfaceRenderer::new(fg_color, bg_color);
+        renderer.font = Some(font);
+        renderer
+    }
+
     /// Returns the renderer to the state it was in when it was first created
     /// (i.e. the foreground and background colours are reset to the values
-    /// given to the constructor, and all other fields are reset).
+    /// given to the constructor, and all other style fields are reset). The
+    /// fonts and missing glyph policy are left unchanged.
     pub fn reset(&mut self) {
         self.fg_color = self.fg_orig;
         self.bg_color = self.bg_orig;
         self.scale = 1;
         self.bold = false;
         self.italic = false;
+        self.line_spacing = 0;
+        self.wrap_width = None;
+        self.h_align = HorizontalAlign::Left;
+        self.v_align = VerticalAlign::Top;
+        self.ellipsis = DEFAULT_ELLIPSIS.to_string();
     }
 
     /// Draws the supplied text to a new surface, which has been sized to fit
-    /// the text exactly, using the renderer's style settings. Returns an `Err`
-    /// result if a character was found which is not in the font, or the font
-    /// could not be initialised.
-    pub fn draw(&self, text: &str) -> Result<Surface, String> {
-        // Create new surface sized to text
-        let width = self.measure_width(text)?;
-        let mut surf = Surface::new(
-            width,
-            UNIFONT_HEIGHT * self.scale,
-            PixelFormatEnum::RGBA8888,
-        )?;
+    /// the text exactly, using the renderer's style settings. Text is split
+    /// into lines at each `\n` or `\r\n`. Returns an `Err` result if a
+    /// character was found which is not in the font, or the font could not be
+    /// initialised.
+    pub fn draw(&self, text: &str) -> Result<Surface<'_>, Error> {
+        self.draw_sized(
+            text,
+            self.measure_width(text)?,
+            self.measure_height(text)?,
+        )
+    }
+
+    /// Draws the supplied text to a new surface of the given size, aligning it
+    /// within the surface according to `h_align` and `v_align`. Any text which
+    /// doesn't fit is cut off at the edges of the surface.
+    pub fn draw_sized(
+        &self,
+        text: &str,
+        width: u32,
+        height: u32,
+    ) -> Result<Surface<'_>, Error> {
+        let mut surf = Surface::new(width, height, PixelFormatEnum::RGBA8888)
+            .map_err(Error::Sdl)?;
 
         // Fill surface with background color
-        surf.fill_rect(None, self.bg_color)?;
+        surf.fill_rect(None, self.bg_color).map_err(Error::Sdl)?;
+
+        self.draw_surface(&mut surf, text, 0, 0, width, height)?;
+
+        Ok(surf)
+    }
+
+    /// Draws the supplied text directly into an existing surface, with the
+    /// top-left corner of the text at (`x`, `y`), rather than allocating a new
+    /// surface. The area behind the text is filled with `bg_color`, unless it
+    /// is fully transparent. Any text outside of the surface is clipped.
+    ///
+    /// The surface may use any RGB or indexed pixel format with at least 8 bits
+    /// per pixel; the colours are mapped through the surface's format (and
+    /// palette), so drawing to a window surface needs no conversion pass.
+    /// Returns `Error::UnsupportedPixelFormat` for any other format.
+    pub fn draw_to(
+        &self,
+        text: &str,
+        surf: &mut SurfaceRef,
+        x: i32,
+        y: i32,
+    ) -> Result<(), Error> {
+        let width = self.measure_width(text)?;
+        let height = self.measure_height(text)?;
+
+        if self.bg_color.a != 0 && width > 0 && height > 0 {
+            surf.fill_rect(Rect::new(x, y, width, height), self.bg_color)
+                .map_err(Error::Sdl)?;
+        }
+
+        self.draw_surface(surf, text, x, y, width, height)
+    }
+
+    /// Locks a surface, and draws the supplied text to it, aligned within the
+    /// box at (`x`, `y`) of the given size.
+    fn draw_surface(
+        &self,
+        surf: &mut SurfaceRef,
+        text: &str,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error> {
+        let bytes_per_pixel = match bytes_per_pixel(surf.pixel_format_enum()) {
+            Some(bytes_per_pixel) => bytes_per_pixel,
+            None => {
+                return Err(Error::UnsupportedPixelFormat(
+                    surf.pixel_format_enum(),
+                ))
+            }
+        };
+
+        // Map the text colour to the surface's format up front, which also
+        // finds the closest palette entry for indexed surfaces
+        let fg_pixel = self.fg_color.to_u32(&surf.pixel_format());
+
+        let pitch = surf.pitch();
+        let surf_width = surf.width();
+        let surf_height = surf.height();
+        let draw = |pixels: &mut [u8]| {
+            let mut canvas = Canvas {
+                pixels,
+                pitch,
+                bytes_per_pixel,
+                width: surf_width,
+                height: surf_height,
+                fg_pixel,
+            };
+            self.draw_raw(&mut canvas, text, x, y, width, height)
+        };
 
         // Obtain raw surface data reference, then draw characters of string
         // through `draw_raw`
         if surf.must_lock() {
-            surf.with_lock_mut(|px: &mut [u8]| self.draw_raw(px, &width, text))?
+            surf.with_lock_mut(draw)
         } else {
-            self.draw_raw(surf.without_lock_mut().unwrap(), &width, text)?
+            draw(surf.without_lock_mut().unwrap())
         }
+    }
 
-        Ok(surf)
+    /// Finds every character in the supplied text which none of the renderer's
+    /// fonts define, without drawing anything. Characters are reported
+    /// whatever the missing glyph policy is, and line breaks are ignored, since
+    /// they're never drawn.
+    pub fn check(&self, text: &str) -> Result<Vec<MissingChar>, Error> {
+        let font = self.font()?;
+        let uses_unifont = self.uses_unifont()?;
+        let mut missing = Vec::new();
+
+        for (char_index, (index, c)) in text.char_indices().enumerate() {
+            if c == '\n' || (c == '\r' && text[index + 1..].starts_with('\n')) {
+                continue;
+            }
+
+            if font.get(c as u32).is_none() {
+                missing.push(MissingChar {
+                    character: c,
+                    index,
+                    char_index,
+                    feature: if uses_unifont {
+                        unifont::feature_for(c as u32)
+                    } else {
+                        None
+                    },
+                });
+            }
+        }
+
+        Ok(missing)
     }
 
-    /// Sums the width of each character in the supplied text, and multiples the
-    /// sum by the renderer's integer scale factor. Takes into consideration
-    /// formatting options' effects on text width.
-    pub fn measure_width(&self, text: &str) -> Result<u32, String> {
-        let mut basic_width = self.scale * count_char_width(text)?;
+    /// Sums the width of each character in each line of the supplied text
+    /// (after wrapping), and multiples the widest line's sum by the renderer's
+    /// integer scale factor. Takes into consideration formatting options'
+    /// effects on text width.
+    pub fn measure_width(&self, text: &str) -> Result<u32, Error> {
+        let font = self.font()?;
+
+        let mut widest = 0;
+        for line in self.layout(text)? {
+            let width = count_char_width(
+                &font,
+                self.missing_glyph,
+                &text[line.clone()],
+                line.start,
+            )?;
+            widest = widest.max(width);
+        }
+
+        Ok(self.scale * widest + self.style_width(&font))
+    }
+
+    /// Returns the height of the supplied text, which is the number of lines in
+    /// it (after wrapping) multiplied by the font's height (16 for Unifont)
+    /// times `scale`, plus `line_spacing` between each line.
+    pub fn measure_height(&self, text: &str) -> Result<u32, Error> {
+        let font_height = self.font()?.height;
+        let lines = self.layout(text)?.len() as u32;
+
+        Ok(lines * self.scale * font_height + (lines - 1) * self.line_spacing)
+    }
+
+    /// Splits the supplied text into the lines that it would be drawn as,
+    /// returning the byte range of each line within the text. Lines are split
+    /// at each `\n` or `\r\n`, and, if `wrap_width` is set, wherever a line
+    /// would be too wide. Lines are preferably wrapped at spaces (which are
+    /// left out of the line ranges), after hyphens, or between CJK characters,
+    /// but words which are too long to fit on a line of their own are broken
+    /// between characters.
+    pub fn layout(&self, text: &str) -> Result<Vec<Range<usize>>, Error> {
+        let lines = split_lines(text);
+
+        let wrap_width = match self.wrap_width {
+            None => return Ok(lines),
+            Some(wrap_width) => wrap_width,
+        };
+
+        // Maximum width of the characters on each line
+        let font = self.font()?;
+        let available = wrap_width.saturating_sub(self.style_width(&font));
+
+        let mut wrapped = Vec::new();
+        for line in lines {
+            let chars = self.char_widths(&font, text, &line)?;
+
+            if chars.is_empty() {
+                wrapped.push(line);
+            } else {
+                wrap_line(&chars, line.end, available, &mut wrapped);
+            }
+        }
+
+        Ok(wrapped)
+    }
+
+    /// Shortens each line of the supplied text which is wider than `max_width`
+    /// pixels (as measured by `measure_width`), by cutting characters from the
+    /// part of the line given by `mode`, and inserting `ellipsis` in their
+    /// place. Lines which already fit are left unchanged. The ellipsis is left
+    /// out too if even it doesn't fit.
+    pub fn truncate<'a>(
+        &self,
+        text: &'a str,
+        max_width: u32,
+        mode: Truncation,
+    ) -> Result<Cow<'a, str>, Error> {
+        let font = self.font()?;
+
+        // Maximum width of the characters on each line
+        let available = max_width.saturating_sub(self.style_width(&font));
+
+        let ellipsis_width = self.scale
+            * count_char_width(&font, self.missing_glyph, &self.ellipsis, 0)?;
+        let (ellipsis, budget) = if ellipsis_width <= available {
+            (self.ellipsis.as_str(), available - ellipsis_width)
+        } else {
+            ("", available)
+        };
+
+        let mut truncated = String::new();
+        let mut copied = 0;
+
+        for line in split_lines(text) {
+            let chars = self.char_widths(&font, text, &line)?;
+            if chars.iter().map(|c| c.2).sum::<u32>() <= available {
+                continue;
+            }
+
+            // Number of characters kept from the start and end of the line
+            let (head, tail) = match mode {
+                Truncation::Start => (0, fit_chars(chars.iter().rev(), budget)),
+                Truncation::Middle => {
+                    let head = fit_chars(chars.iter(), budget.div_ceil(2));
+                    let head_width: u32 =
+                        chars[..head].iter().map(|c| c.2).sum();
+                    let tail =
+                        fit_chars(chars.iter().rev(), budget - head_width);
+                    (head, tail)
+                }
+                Truncation::End => (fit_chars(chars.iter(), budget), 0),
+            };
+
+            let head_end = chars.get(head).map_or(line.end, |c| c.0);
+            let tail_start = if tail == 0 {
+                line.end
+            } else {
+                chars[chars.len() - tail].0
+            };
+
+            truncated.push_str(&text[copied..head_end]);
+            truncated.push_str(ellipsis);
+            truncated.push_str(&text[tail_start..line.end]);
+            copied = line.end;
+        }
+
+        if copied == 0 {
+            return Ok(Cow::Borrowed(text));
+        }
+
+        truncated.push_str(&text[copied..]);
+        Ok(Cow::Owned(truncated))
+    }
+
+    /// Returns the byte index, character and scaled width of each character in
+    /// a single line of text, given the line's byte range within the text.
+    fn char_widths(
+        &self,
+        font: &FontStack,
+        text: &str,
+        line: &Range<usize>,
+    ) -> Result<Vec<(usize, char, u32)>, Error> {
+        let mut chars = Vec::new();
+
+        for (index, c) in text[line.clone()].char_indices() {
+            let index = line.start + index;
+            let width = match get_glyph(font, self.missing_glyph, c, index)? {
+                None => 0,
+                Some(found) => self.scale * found.glyph().width() as u32,
+            };
+            chars.push((index, c, width));
+        }
+
+        Ok(chars)
+    }
+
+    /// Returns the extra width added to each line by the formatting options,
+    /// when drawn with the supplied font.
+    fn style_width(&self, font: &FontStack) -> u32 {
+        let mut width = 0;
 
         if self.bold {
-            basic_width += self.scale;
+            width += self.scale;
         }
         if self.italic {
-            basic_width += 8 * self.scale;
+            width += self.italic_shift(font, 0);
+        }
+
+        width
+    }
+
+    /// Returns whether the embedded Unifont is one of the fonts that text is
+    /// drawn with, so that its cargo features decide what can be drawn.
+    fn uses_unifont(&self) -> Result<bool, Error> {
+        if self.font.is_none() {
+            return Ok(true);
         }
 
-        Ok(basic_width)
+        // The font may have been set to the embedded Unifont explicitly
+        let unifont = unifont::get_unifont()?;
+        Ok(self
+            .font
+            .iter()
+            .chain(self.overlay.iter())
+            .chain(self.fallbacks.iter())
+            .any(|font| Arc::ptr_eq(font, &unifont)))
     }
 
-    /// May in the future take into consideration newlines and other formatting.
-    /// For now, it just returns `16 * scale`, thus, the result of this method
-    /// can always be safely `unwrap()`ped.
-    pub fn measure_height(&self, _text: &str) -> Result<u32, String> {
-        Ok(self.scale * UNIFONT_HEIGHT)
+    /// Returns the fonts that text is drawn with.
+    fn font(&self) -> Result<FontStack<'_>, Error> {
+        FontStack::new(&self.font, self.overlay.as_deref(), &self.fallbacks)
     }
 
-    /// Takes an array of pixels and draws the supplied text to it, using the
-    /// specified render options. This function always assumes RGBA8888 pixel
-    /// formatting.
+    /// Draws the supplied text to a canvas, aligning it within the box at
+    /// (`x`, `y`) of the given size.
     fn draw_raw(
         &self,
-        pixels: &mut [u8],
-        surf_width: &u32,
+        canvas: &mut Canvas,
         text: &str,
-    ) -> Result<(), String> {
-        let unifont = get_unifont()?;
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error> {
+        let font = self.font()?;
+        let lines = self.layout(text)?;
 
-        // Start position of next character
-        let mut x_offset = 0;
-
-        let iter = text.chars();
-        for c in iter {
-            // Retrieve character description from hashmap
-            let font_char = match unifont.get(&(c as u32)) {
-                None => return Err(gen_missing_char_str(&c)),
-                Some(font_char) => font_char,
+        let glyph_height = font.height * self.scale;
+        let line_count = lines.len() as u32;
+        let text_height =
+            line_count * glyph_height + (line_count - 1) * self.line_spacing;
+
+        // Vertical position of the next line
+        let v_slack = height as i32 - text_height as i32;
+        let mut y_offset = y + match self.v_align {
+            VerticalAlign::Top => 0,
+            VerticalAlign::Middle => v_slack / 2,
+            VerticalAlign::Bottom => v_slack,
+        };
+
+        for line in lines {
+            let line_text = &text[line.clone()];
+            let line_width = self.scale
+                * count_char_width(
+                    &font,
+                    self.missing_glyph,
+                    line_text,
+                    line.start,
+                )?
+                + self.style_width(&font);
+
+            let h_slack = width as i32 - line_width as i32;
+            let mut justify = Justify::default();
+            let x_offset = x + match self.h_align {
+                HorizontalAlign::Left => 0,
+                HorizontalAlign::Center => h_slack / 2,
+                HorizontalAlign::Right => h_slack,
+                HorizontalAlign::Justify => {
+                    if h_slack > 0 && !ends_paragraph(text, &line) {
+                        justify = Justify::new(h_slack as u32, line_text);
+                    }
+                    0
+                }
             };
 
+            self.draw_line(
+                canvas, &font, line_text, line.start, x_offset, y_offset,
+                justify,
+            )?;
+
+            y_offset += (glyph_height + self.line_spacing) as i32;
+        }
+
+        Ok(())
+    }
+
+    /// Draws a single line of text to a canvas, with the top-left corner of
+    /// the line at (`x`, `y`). `offset` is the byte index of the line within
+    /// the whole text, and is used when reporting missing characters.
+    #[allow(clippy::too_many_arguments)]
+    fn draw_line(
+        &self,
+        canvas: &mut Canvas,
+        font: &FontStack,
+        text: &str,
+        offset: usize,
+        x: i32,
+        y: i32,
+        justify: Justify,
+    ) -> Result<(), Error> {
+        // Start position of next character
+        let mut x_offset = x;
+        // Number of justified spaces passed so far
+        let mut gap = 0;
+        let mut seen_text = false;
+
+        let iter = text.char_indices();
+        for (index, c) in iter {
+            // Widen the spaces between words of justified lines
+            if !c.is_whitespace() {
+                seen_text = true;
+            } else if seen_text {
+                x_offset += justify.gap_width(gap) as i32;
+                gap += 1;
+            }
+
+            // Retrieve character description from the font
+            let found =
+                match get_glyph(font, self.missing_glyph, c, offset + index)? {
+                    None => continue,
+                    Some(found) => found,
+                };
+            let glyph = found.glyph();
+            let top = found.top(font.ascent);
+
             // Draw rows of character bitmap
-            for row in 0..UNIFONT_HEIGHT as usize {
+            for row in 0..glyph.height() as u32 {
                 // Draw each pixel for a row
-                for col in (0..font_char.width as usize).rev() {
-                    if font_char.bitmap[row].get_bit(col) {
-                        // Double character on x axis if we're bolding
-                        for x in if self.bold {
-                            0..self.scale * 2
-                        } else {
-                            0..self.scale
-                        } {
-                            for y in 0..self.scale {
-                                // Calculate the byte position of the pixel
-                                // (this thing is a mess, to be honest)
-                                let px_base = (4
-                                    * surf_width
-                                    * (row as u32 * self.scale + y)
-                                    + 4 * x_offset
-                                    + 4 * (font_char.width as u32 * self.scale
-                                        - col as u32 * self.scale
-                                        - self.scale)
-                                    + 4 * x)
-                                    as usize;
-
-                                // Insert fg colour into the current pixel
-                                // TODO assumes little endian
-                                pixels[px_base + 3] = self.fg_color.r;
-                                pixels[px_base + 2] = self.fg_color.g;
-                                pixels[px_base + 1] = self.fg_color.b;
-                                pixels[px_base] = self.fg_color.a;
-                            }
+                for col in 0..glyph.width() as u32 {
+                    if !glyph.pixel(col, row) {
+                        continue;
+                    }
+
+                    // Double character on x axis if we're bolding
+                    for px_x in if self.bold {
+                        0..self.scale * 2
+                    } else {
+                        0..self.scale
+                    } {
+                        for px_y in 0..self.scale {
+                            let px_row = (top + row) * self.scale + px_y;
+                            let px_col = col * self.scale
+                                + px_x
+                                + self.italic_shift(font, px_row);
+
+                            canvas.put(
+                                x_offset + px_col as i32,
+                                y + px_row as i32,
+                            );
                         }
                     }
                 }
             }
 
             // Shift next character
-            x_offset += self.scale * font_char.width as u32;
+            x_offset += (self.scale * glyph.width() as u32) as i32;
         }
 
-        // Italicise text
-        if self.italic {
-            let mut offset = (UNIFONT_HEIGHT * self.scale) / 2;
-            for row in 0..UNIFONT_HEIGHT * self.scale as u32 {
-                let row_offset = 4 * row * surf_width;
-                // Shift bytes forward
-                for i in
-                    (row_offset..row_offset + 4 * (surf_width - offset)).rev()
-                {
-                    pixels[(i + 4 * offset) as usize] = pixels[i as usize];
-                }
-
-                // Clear space behind first character
-                for i in (row_offset as usize
-                    ..(row_offset + offset * 4) as usize)
-                    .step_by(4)
-                {
-                    // TODO assumes little endian
-                    pixels[i + 3] = self.bg_color.r;
-                    pixels[i + 2] = self.bg_color.g;
-                    pixels[i + 1] = self.bg_color.b;
-                    pixels[i] = self.bg_color.a;
-                }
+        Ok(())
+    }
 
-                if row % 2 == 1 {
-                    offset -= 1;
-                }
-            }
+    /// Returns how far to the right a pixel row of a line is shifted when the
+    /// text is italicised. Every two rows are shifted by one pixel less than
+    /// the two above them, from half the line height at the top, to one pixel
+    /// at the bottom.
+    fn italic_shift(&self, font: &FontStack, row: u32) -> u32 {
+        if self.italic {
+            (font.height * self.scale) / 2 - row / 2
+        } else {
+            0
         }
-
-        Ok(())
     }
 }
 
+/// Both renderers only hold plain settings and shared, immutable fonts, and the
+/// embedded font is only ever loaded or unloaded behind a lock, so they can be
+/// freely shared with or moved to other threads. This fails to compile if that
+/// ever stops being true.
+#[allow(dead_code)]
+fn assert_renderers_send_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<SurfaceRenderer>();
+    assert_send_sync::<FormattedRenderer>();
+}
+
 /// Advanced renderer with additional capabilities.
 pub struct FormattedRenderer {
     /// Stores variables and string literals. The boolean value is set to `true`
@@ -218,6 +699,20 @@ pub struct FormattedRenderer {
     bg_color: Color,
     /// The scale to use for all text.
     scale: u32,
+    /// Number of extra pixels to leave between each line of text.
+    line_spacing: u32,
+    /// How each line is aligned horizontally.
+    h_align: HorizontalAlign,
+    /// How the text is aligned vertically, when drawn with `draw_sized`.
+    v_align: VerticalAlign,
+    /// The font to use for all text.
+    font: Option<Arc<Font>>,
+    /// Characters which take priority over those in the font, for all text.
+    overlay: Option<Arc<Font>>,
+    /// Fonts tried in order for characters which aren't in the font.
+    fallbacks: Vec<Arc<Font>>,
+    /// The missing glyph policy to use for all text.
+    missing_glyph: MissingGlyphPolicy,
 }
 
 impl FormattedRenderer {
@@ -230,6 +725,13 @@ impl FormattedRenderer {
             variables: HashMap::new(),
             bg_color,
             scale: 1,
+            line_spacing: 0,
+            h_align: HorizontalAlign::Left,
+            v_align: VerticalAlign::Top,
+            font: None,
+            overlay: None,
+            fallbacks: Vec::new(),
+            missing_glyph: MissingGlyphPolicy::Error,
         }
     }
 
@@ -247,6 +749,10 @@ impl FormattedRenderer {
         renderer.bold = bold;
         renderer.italic = italic;
         renderer.scale = self.scale;
+        renderer.font = self.font.clone();
+        renderer.overlay = self.overlay.clone();
+        renderer.fallbacks = self.fallbacks.clone();
+        renderer.missing_glyph = self.missing_glyph;
         self.renderers.push(renderer);
     }
 
@@ -264,6 +770,10 @@ impl FormattedRenderer {
         renderer.bold = bold;
         renderer.italic = italic;
         renderer.scale = self.scale;
+        renderer.font = self.font.clone();
+        renderer.overlay = self.overlay.clone();
+        renderer.fallbacks = self.fallbacks.clone();
+        renderer.missing_glyph = self.missing_glyph;
         self.renderers.push(renderer);
         self.variables
             .insert(name.to_string(), "#UNDEFINED".to_string());
@@ -289,7 +799,7 @@ impl FormattedRenderer {
     /// it's been changed by modifying a renderer's background colour through
     /// the `iter_mut` method).
     pub fn get_bg_color(&self) -> Color {
-        return self.bg_color;
+        self.bg_color
     }
 
     /// Sets the scale of each component in the formatted output.
@@ -302,77 +812,318 @@ impl FormattedRenderer {
 
     /// Gets the current scale factor used for draw operations.
     pub fn get_scale(&self) -> u32 {
-        return self.scale;
+        self.scale
+    }
+
+    /// Sets the number of extra pixels to leave between each line of text. Not
+    /// affected by the scale.
+    pub fn set_line_spacing(&mut self, line_spacing: u32) {
+        self.line_spacing = line_spacing;
+    }
+
+    /// Gets the number of extra pixels left between each line of text.
+    pub fn get_line_spacing(&self) -> u32 {
+        self.line_spacing
+    }
+
+    /// Sets how each line of the formatted output is aligned horizontally.
+    pub fn set_h_align(&mut self, h_align: HorizontalAlign) {
+        self.h_align = h_align;
+    }
+
+    /// Gets how each line of the formatted output is aligned horizontally.
+    pub fn get_h_align(&self) -> HorizontalAlign {
+        self.h_align
+    }
+
+    /// Sets how the formatted output is aligned vertically, when drawn with
+    /// `draw_sized`.
+    pub fn set_v_align(&mut self, v_align: VerticalAlign) {
+        self.v_align = v_align;
+    }
+
+    /// Gets how the formatted output is aligned vertically.
+    pub fn get_v_align(&self) -> VerticalAlign {
+        self.v_align
+    }
+
+    /// Sets the font used by each component in the formatted output. The
+    /// embedded Unifont is used if `None`.
+    pub fn set_font(&mut self, font: Option<Arc<Font>>) {
+        for renderer in self.renderers.iter_mut() {
+            renderer.font = font.clone();
+        }
+        self.font = font;
+    }
+
+    /// Gets the font used for draw operations, or `None` if the embedded
+    /// Unifont is being used.
+    pub fn get_font(&self) -> Option<&Arc<Font>> {
+        self.font.as_ref()
+    }
+
+    /// Sets the characters which take priority over those in the font, for
+    /// each component in the formatted output.
+    pub fn set_overlay(&mut self, overlay: Option<Arc<Font>>) {
+        for renderer in self.renderers.iter_mut() {
+            renderer.overlay = overlay.clone();
+        }
+        self.overlay = overlay;
+    }
+
+    /// Gets the characters which take priority over those in the font, or
+    /// `None` if there aren't any.
+    pub fn get_overlay(&self) -> Option<&Arc<Font>> {
+        self.overlay.as_ref()
+    }
+
+    /// Sets the fonts which are tried in order, by each component in the
+    /// formatted output, for characters which aren't in the font.
+    pub fn set_fallbacks(&mut self, fallbacks: Vec<Arc<Font>>) {
+        for renderer in self.renderers.iter_mut() {
+            renderer.fallbacks = fallbacks.clone();
+        }
+        self.fallbacks = fallbacks;
+    }
+
+    /// Gets the fonts which are tried in order for characters which aren't in
+    /// the font.
+    pub fn get_fallbacks(&self) -> &[Arc<Font>] {
+        &self.fallbacks
+    }
+
+    /// Sets the policy used by each component in the formatted output for
+    /// characters which aren't in the font.
+    pub fn set_missing_glyph(&mut self, missing_glyph: MissingGlyphPolicy) {
+        self.missing_glyph = missing_glyph;
+        for renderer in self.renderers.iter_mut() {
+            renderer.missing_glyph = missing_glyph;
+        }
+    }
+
+    /// Gets the policy used for characters which aren't in the font.
+    pub fn get_missing_glyph(&self) -> MissingGlyphPolicy {
+        self.missing_glyph
     }
 
     /// Returns an iterator over each renderer, which allows the renderers'
     /// settings to be modified.
-    pub fn iter_mut(&mut self) -> IterMut<SurfaceRenderer> {
+    pub fn iter_mut(&mut self) -> IterMut<'_, SurfaceRenderer> {
         self.renderers.iter_mut()
     }
 
     /// Sequentially draws each literal and variable, using its associated
-    /// renderer, and linearly appends the output surfaces.
-    pub fn draw<'a>(&self) -> Result<Surface<'a>, String> {
-        // Preflight width sum
-        let width = self.measure_width()?;
+    /// renderer, and linearly appends the output surfaces. A line break in any
+    /// section starts a new line, which the following sections continue on.
+    pub fn draw<'a>(&self) -> Result<Surface<'a>, Error> {
+        let (runs, line_widths) = self.layout()?;
+        let width = line_widths.iter().cloned().max().unwrap_or(0);
+        let height = self.height_of(line_widths.len())?;
 
+        self.draw_layout(&runs, &line_widths, width, height)
+    }
+
+    /// Draws the formatted text to a new surface of the given size, aligning
+    /// it within the surface in the same way as `SurfaceRenderer::draw_sized`.
+    /// Since formatted text is never wrapped, justified lines are aligned to
+    /// the left. The whole surface, including any space left around the text,
+    /// is filled with the background colour.
+    pub fn draw_sized<'a>(
+        &self,
+        width: u32,
+        height: u32,
+    ) -> Result<Surface<'a>, Error> {
+        let (runs, line_widths) = self.layout()?;
+
+        self.draw_layout(&runs, &line_widths, width, height)
+    }
+
+    /// Draws the formatted text directly into an existing surface, with the
+    /// top-left corner of the text at (`x`, `y`), in the same way as
+    /// `SurfaceRenderer::draw_to`. The whole box behind the text is filled with
+    /// the background colour, unless it is fully transparent.
+    pub fn draw_to(
+        &self,
+        surf: &mut SurfaceRef,
+        x: i32,
+        y: i32,
+    ) -> Result<(), Error> {
+        let (runs, line_widths) = self.layout()?;
+        let width = line_widths.iter().cloned().max().unwrap_or(0);
+        let height = self.height_of(line_widths.len())?;
+
+        // Fill the whole box, since each run only fills its own part of its
+        // line
+        if self.bg_color.a != 0 && width > 0 && height > 0 {
+            surf.fill_rect(Rect::new(x, y, width, height), self.bg_color)
+                .map_err(Error::Sdl)?;
+        }
+
+        self.draw_layout_to(surf, &runs, &line_widths, x, y, width, height)
+    }
+
+    /// Measures the width of the widest line of the contained text, including
+    /// variable values, taking into consideration the formatting of each
+    /// section.
+    pub fn measure_width(&self) -> Result<u32, Error> {
+        let (_, line_widths) = self.layout()?;
+
+        Ok(line_widths.into_iter().max().unwrap_or(0))
+    }
+
+    /// Returns the height of all content in the formatted string, which is the
+    /// number of lines multiplied by the font's height (16 for Unifont) times
+    /// `scale`, plus the line spacing between each line.
+    pub fn measure_height(&self) -> Result<u32, Error> {
+        let (_, line_widths) = self.layout()?;
+
+        self.height_of(line_widths.len())
+    }
+
+    /// Draws laid out runs of text to a new surface of the given size.
+    fn draw_layout<'a>(
+        &self,
+        runs: &[Run],
+        line_widths: &[u32],
+        width: u32,
+        height: u32,
+    ) -> Result<Surface<'a>, Error> {
         // Create output surface
-        let mut surf = Surface::new(
-            width,
-            UNIFONT_HEIGHT * self.scale,
-            PixelFormatEnum::RGBA8888,
-        )?;
+        let mut surf = Surface::new(width, height, PixelFormatEnum::RGBA8888)
+            .map_err(Error::Sdl)?;
+
+        // Fill surface with background color, since each run only fills its
+        // own part of its line
+        surf.fill_rect(None, self.bg_color).map_err(Error::Sdl)?;
+
+        self.draw_layout_to(&mut surf, runs, line_widths, 0, 0, width, height)?;
+
+        Ok(surf)
+    }
+
+    /// Draws laid out runs of text to an existing surface, aligned within the
+    /// box at (`x`, `y`) of the given size.
+    #[allow(clippy::too_many_arguments)]
+    fn draw_layout_to(
+        &self,
+        surf: &mut SurfaceRef,
+        runs: &[Run],
+        line_widths: &[u32],
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error> {
+        let v_slack = height as i32 - self.height_of(line_widths.len())? as i32;
+        let y = y + match self.v_align {
+            VerticalAlign::Top => 0,
+            VerticalAlign::Middle => v_slack / 2,
+            VerticalAlign::Bottom => v_slack,
+        };
 
         // Draw text
-        let mut offset: u32 = 0;
-        for (text, renderer) in
-            (&self.text).into_iter().zip((&self.renderers).into_iter())
-        {
-            let text = if text.0 {
-                &text.1
-            } else {
-                match self.variables.get(&text.1) {
-                    Some(val) => val,
-                    None => return Err("Undefined variable used".to_string()),
-                }
+        let line_height = self.font_height()? * self.scale + self.line_spacing;
+        for run in runs {
+            let h_slack = width as i32 - line_widths[run.line] as i32;
+            let x = x + match self.h_align {
+                HorizontalAlign::Left | HorizontalAlign::Justify => 0,
+                HorizontalAlign::Center => h_slack / 2,
+                HorizontalAlign::Right => h_slack,
             };
 
-            renderer.draw(text)?.blit(
-                None,
-                &mut surf,
-                Rect::new(offset as i32, 0, 0, 0),
+            run.renderer.draw_to(
+                run.text,
+                surf,
+                x + run.x as i32,
+                y + (run.line as u32 * line_height) as i32,
             )?;
-            offset += renderer.measure_width(text)?;
         }
 
-        Ok(surf)
+        Ok(())
     }
 
-    /// Measures the width of all of the contained text, including variable
-    /// values, taking into consideration the formatting of each section.
-    pub fn measure_width(&self) -> Result<u32, String> {
-        let mut width = 0;
-        for (text, renderer) in
-            (&self.text).into_iter().zip((&self.renderers).into_iter())
-        {
-            if text.0 {
-                width += renderer.measure_width(&text.1)?;
-            } else {
-                match self.variables.get(&text.1) {
-                    Some(val) => width += renderer.measure_width(val)?,
-                    None => return Err("Undefined variable used".to_string()),
+    /// Returns the height taken up by the supplied number of lines.
+    fn height_of(&self, lines: usize) -> Result<u32, Error> {
+        let lines = lines as u32;
+
+        Ok(lines * self.scale * self.font_height()?
+            + (lines - 1) * self.line_spacing)
+    }
+
+    /// Returns the height of each line, which fits the characters of the
+    /// font, the overlay and every fallback font.
+    fn font_height(&self) -> Result<u32, Error> {
+        let fonts = FontStack::new(
+            &self.font,
+            self.overlay.as_deref(),
+            &self.fallbacks,
+        )?;
+
+        Ok(fonts.height)
+    }
+
+    /// Returns the text of a literal, or the value of a variable.
+    fn segment_text<'a>(
+        &'a self,
+        text: &'a (bool, String),
+    ) -> Result<&'a str, Error> {
+        if text.0 {
+            Ok(&text.1)
+        } else {
+            match self.variables.get(&text.1) {
+                Some(val) => Ok(val),
+                None => Err(Error::UndefinedVariable(text.1.clone())),
+            }
+        }
+    }
+
+    /// Splits the contained text into runs, which each lie on a single line
+    /// and are drawn by a single renderer. Also returns the width of each line.
+    fn layout(&self) -> Result<(Vec<Run<'_>>, Vec<u32>), Error> {
+        let mut runs = Vec::new();
+        let mut line_widths = vec![0];
+
+        for (text, renderer) in self.text.iter().zip(self.renderers.iter()) {
+            let text = self.segment_text(text)?;
+
+            for (i, line) in split_lines(text).into_iter().enumerate() {
+                if i > 0 {
+                    line_widths.push(0);
+                }
+
+                let text = &text[line];
+                if text.is_empty() {
+                    continue;
                 }
+
+                let line = line_widths.len() - 1;
+                let x = line_widths[line];
+                line_widths[line] += renderer.measure_width(text)?;
+
+                runs.push(Run {
+                    renderer,
+                    text,
+                    line,
+                    x,
+                });
             }
         }
 
-        Ok(width)
+        Ok((runs, line_widths))
     }
+}
 
-    /// Returns the height of all content in the formatted string.
-    pub fn measure_height(&self) -> Result<u32, String> {
-        Ok(self.scale * UNIFONT_HEIGHT)
-    }
+/// A piece of a `FormattedRenderer`'s text, which lies on a single line and is
+/// drawn by a single renderer.
+struct Run<'a> {
+    /// The renderer to draw the text with
+    renderer: &'a SurfaceRenderer,
+    /// The text to draw, which doesn't contain any line breaks
+    text: &'a str,
+    /// Index of the line that the run is on
+    line: usize,
+    /// Horizontal position of the run within its line
+    x: u32,
 }
 
 impl IntoIterator for FormattedRenderer {
@@ -384,40 +1135,691 @@ impl IntoIterator for FormattedRenderer {
     }
 }
 
-/// Maps `unifont`'s `Result` error type to ours, so that the `?` operator
-/// can be utilised.
-fn get_unifont<'a>() -> Result<&'a unifont::FontChars, String> {
-    match unifont::get_unifont() {
-        Ok(unifont) => Ok(unifont),
-        Err(_) => {
-            return Err("Failed to initialise embedded Unifont".to_string())
+/// The locked pixels of a surface, which text is drawn into.
+struct Canvas<'a> {
+    /// Raw pixel data, in the surface's pixel format
+    pixels: &'a mut [u8],
+    /// Number of bytes in each row of pixels
+    pitch: u32,
+    /// Number of bytes used by each pixel
+    bytes_per_pixel: u32,
+    /// Width of the surface in pixels
+    width: u32,
+    /// Height of the surface in pixels
+    height: u32,
+    /// The text colour, mapped to the surface's pixel format
+    fg_pixel: u32,
+}
+
+impl<'a> Canvas<'a> {
+    /// Sets a single pixel to the text colour. Pixels outside of the surface
+    /// are ignored, so that text is clipped to the surface's bounds.
+    fn put(&mut self, x: i32, y: i32) {
+        if x < 0 || y < 0 || x as u32 >= self.width || y as u32 >= self.height {
+            return;
         }
+
+        // Calculate the byte position of the pixel
+        let px_base =
+            (self.pitch * y as u32 + self.bytes_per_pixel * x as u32) as usize;
+        let pixel = &mut self.pixels[px_base..];
+
+        // SDL stores pixels as native-endian integers of the format's size,
+        // with 24-bit pixels taking the low three bytes of the value
+        let fg = self.fg_pixel;
+        match self.bytes_per_pixel {
+            1 => pixel[0] = fg as u8,
+            2 => pixel[..2].copy_from_slice(&(fg as u16).to_ne_bytes()),
+            3 if cfg!(target_endian = "big") => {
+                pixel[..3].copy_from_slice(&fg.to_be_bytes()[1..])
+            }
+            3 => pixel[..3].copy_from_slice(&fg.to_le_bytes()[..3]),
+            _ => pixel[..4].copy_from_slice(&fg.to_ne_bytes()),
+        }
+    }
+}
+
+/// Returns the number of bytes used by each pixel of a format that text can be
+/// drawn into, or `None` if the format isn't supported (i.e. indexed formats
+/// with less than 8 bits per pixel, and YUV formats).
+fn bytes_per_pixel(format: PixelFormatEnum) -> Option<u32> {
+    match format {
+        PixelFormatEnum::Unknown
+        | PixelFormatEnum::Index1LSB
+        | PixelFormatEnum::Index1MSB
+        | PixelFormatEnum::Index4LSB
+        | PixelFormatEnum::Index4MSB
+        | PixelFormatEnum::YV12
+        | PixelFormatEnum::IYUV
+        | PixelFormatEnum::YUY2
+        | PixelFormatEnum::UYVY
+        | PixelFormatEnum::YVYU => None,
+        format => Some(format.byte_size_per_pixel() as u32),
     }
 }
 
-/// Finds the rendered width of a string, taking into consideration whether each
-/// character is half-width (8px) or full-width (16px). Returns an error result
-/// if a character is not found in the font (i.e. the feature to include it was
-/// probably not enabled).
-fn count_char_width(text: &str) -> Result<u32, String> {
-    let unifont = get_unifont()?;
+/// Describes how much space to add between the words of a justified line.
+#[derive(Clone, Copy, Default)]
+struct Justify {
+    /// Total number of pixels to add to the line
+    extra: u32,
+    /// Number of spaces in the line that the pixels are shared between
+    gaps: u32,
+}
+
+impl Justify {
+    /// Shares the supplied number of pixels between the spaces of a line.
+    /// Indentation at the start of the line isn't widened.
+    fn new(extra: u32, line: &str) -> Justify {
+        let gaps = line
+            .trim_start()
+            .chars()
+            .filter(|c| c.is_whitespace())
+            .count() as u32;
+
+        Justify { extra, gaps }
+    }
+
+    /// Returns the number of pixels to add to the `n`th space of the line. Any
+    /// pixels which can't be shared evenly go to the first spaces.
+    fn gap_width(&self, n: u32) -> u32 {
+        if self.gaps == 0 {
+            0
+        } else if n < self.extra % self.gaps {
+            self.extra / self.gaps + 1
+        } else {
+            self.extra / self.gaps
+        }
+    }
+}
+
+/// Returns whether a line ends its paragraph, i.e. is followed by a line break
+/// or the end of the text, rather than having been wrapped.
+fn ends_paragraph(text: &str, line: &Range<usize>) -> bool {
+    let rest = &text[line.end..];
+
+    rest.is_empty() || rest.starts_with('\n') || rest.starts_with("\r\n")
+}
+
+/// A character definition found by `get_glyph`: either one from a font
+/// (along with that font's ascent), or one synthesised because none of the
+/// fonts define the character.
+enum FoundGlyph<'a> {
+    Font(Glyph<'a>, u8),
+    Synthesised(FontChar),
+}
+
+impl<'a> FoundGlyph<'a> {
+    fn glyph(&self) -> Glyph<'_> {
+        match *self {
+            FoundGlyph::Font(glyph, _) => glyph,
+            FoundGlyph::Synthesised(ref font_char) => font_char.glyph(),
+        }
+    }
+
+    /// Returns how many rows below the top of a line the character is drawn,
+    /// so that it sits on the line's baseline.
+    fn top(&self, line_ascent: u32) -> u32 {
+        match *self {
+            FoundGlyph::Font(_, ascent) => line_ascent - ascent as u32,
+            // Synthesised characters are as tall as the line
+            FoundGlyph::Synthesised(_) => 0,
+        }
+    }
+}
+
+/// The fonts which characters are looked up in when drawing or measuring text,
+/// and the metrics of the lines that they are drawn in. Holds on to the
+/// embedded Unifont, if it's used, so that it can't be unloaded mid-draw.
+struct FontStack<'a> {
+    /// Takes priority over `font`
+    overlay: Option<&'a Font>,
+    /// The renderer's font, or the embedded Unifont
+    font: Arc<Font>,
+    /// Tried in order after `font`
+    fallbacks: &'a [Arc<Font>],
+    /// Number of rows of each line above the baseline, which is the greatest
+    /// ascent of any of the fonts
+    ascent: u32,
+    /// Number of rows in each line, which fits every font's characters
+    height: u32,
+}
+
+impl<'a> FontStack<'a> {
+    /// Collects the fonts that a renderer draws text with. The embedded
+    /// Unifont is used if `font` is `None`.
+    fn new(
+        font: &Option<Arc<Font>>,
+        overlay: Option<&'a Font>,
+        fallbacks: &'a [Arc<Font>],
+    ) -> Result<FontStack<'a>, Error> {
+        let font = match *font {
+            Some(ref font) => font.clone(),
+            None => unifont::get_unifont()?,
+        };
+
+        let mut stack = FontStack {
+            overlay,
+            font,
+            fallbacks,
+            ascent: 0,
+            height: 0,
+        };
+
+        let (mut ascent, mut descent) = (0, 0);
+        for font in stack.fonts() {
+            ascent = ascent.max(font.ascent() as u32);
+            descent = descent.max((font.height() - font.ascent()) as u32);
+        }
+        stack.ascent = ascent;
+        stack.height = ascent + descent;
+
+        Ok(stack)
+    }
+
+    /// Returns each of the fonts, in the order that they're searched.
+    fn fonts(&self) -> impl Iterator<Item = &Font> {
+        self.overlay
+            .into_iter()
+            .chain(Some(&*self.font))
+            .chain(self.fallbacks.iter().map(|font| &**font))
+    }
+
+    /// Looks up a character in each font in turn, returning it along with the
+    /// ascent of the font that it was found in.
+    fn get(&self, codepoint: u32) -> Option<(Glyph<'_>, u8)> {
+        self.fonts().find_map(|font| {
+            font.get(codepoint).map(|glyph| (glyph, font.ascent()))
+        })
+    }
+}
+
+/// Looks up a character in the fonts, applying the missing glyph policy if it
+/// isn't defined. `None` is returned for characters which should be skipped.
+fn get_glyph<'a>(
+    font: &'a FontStack<'_>,
+    policy: MissingGlyphPolicy,
+    c: char,
+    index: usize,
+) -> Result<Option<FoundGlyph<'a>>, Error> {
+    if let Some((glyph, ascent)) = font.get(c as u32) {
+        return Ok(Some(FoundGlyph::Font(glyph, ascent)));
+    }
+
+    let missing = Error::MissingGlyph {
+        character: c,
+        index,
+    };
+
+    match policy {
+        MissingGlyphPolicy::Error => Err(missing),
+        MissingGlyphPolicy::Skip => Ok(None),
+        MissingGlyphPolicy::Replace(replacement) => {
+            match font.get(replacement as u32) {
+                Some((glyph, ascent)) => {
+                    Ok(Some(FoundGlyph::Font(glyph, ascent)))
+                }
+                None => Err(missing),
+            }
+        }
+        MissingGlyphPolicy::HexBox => Ok(Some(FoundGlyph::Synthesised(
+            FontChar::hex_box_sized(c as u32, font.height.min(255) as u8),
+        ))),
+    }
+}
+
+/// Splits text into lines at each `\n` or `\r\n`, returning the byte range of
+/// each line (excluding its line break). There is always at least one line.
+fn split_lines(text: &str) -> Vec<Range<usize>> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+
+    for (i, _) in text.match_indices('\n') {
+        let end = if text[start..i].ends_with('\r') {
+            i - 1
+        } else {
+            i
+        };
+        lines.push(start..end);
+        start = i + 1;
+    }
+    lines.push(start..text.len());
+
+    lines
+}
+
+/// Counts how many of the supplied characters fit within `width` pixels, given
+/// the byte index, character and width of each character.
+fn fit_chars<'a, I>(chars: I, width: u32) -> usize
+where
+    I: Iterator<Item = &'a (usize, char, u32)>,
+{
+    let mut used = 0;
+
+    chars
+        .take_while(|c| {
+            used += c.2;
+            used <= width
+        })
+        .count()
+}
+
+/// Greedily wraps a single line of text, given the byte index, character and
+/// width of each character in it, and the byte index of the end of the line.
+/// The byte range of each wrapped line is appended to `lines`.
+fn wrap_line(
+    chars: &[(usize, char, u32)],
+    end: usize,
+    available: u32,
+    lines: &mut Vec<Range<usize>>,
+) {
+    // Index into `chars` of the first character of the current line
+    let mut start = 0;
+    let mut width = 0;
+    // Index of the last character which the current line could be broken
+    // before
+    let mut last_break = None;
+
+    let mut i = 0;
+    while i < chars.len() {
+        let (_, c, char_width) = chars[i];
+
+        if i > start && linebreak::is_break_opportunity(chars[i - 1].1, c) {
+            last_break = Some(i);
+        }
+
+        // Spaces may overhang the end of a line, since they aren't drawn there
+        if i > start && !c.is_whitespace() && width + char_width > available {
+            // Fall back to breaking between characters if there's nowhere
+            // better to break the line, or breaking there would leave the line
+            // blank
+            let blank = |end: usize| {
+                chars[start..end].iter().all(|c| c.1.is_whitespace())
+            };
+            let line_end = match last_break {
+                Some(b) if !blank(b) => b,
+                _ => i,
+            };
+
+            // Trailing spaces are left out of the wrapped line
+            let mut trimmed = line_end;
+            while trimmed > start && chars[trimmed - 1].1.is_whitespace() {
+                trimmed -= 1;
+            }
+            lines.push(chars[start].0..chars[trimmed].0);
 
+            // Carry the part of the word that didn't fit onto the next line,
+            // and try adding the current character again
+            start = line_end;
+            width = chars[start..i].iter().map(|c| c.2).sum();
+            last_break = None;
+            continue;
+        }
+
+        width += char_width;
+        i += 1;
+    }
+
+    lines.push(chars[start].0..end);
+}
+
+/// Finds the rendered width of a single line of text, taking into
+/// consideration whether each character is half-width (8px), full-width (16px)
+/// or wider still (24 or 32px). Characters which are not found in the font
+/// (i.e. the feature to include them was probably not enabled) are handled
+/// according to the supplied policy. `offset` is the byte index of the line
+/// within the whole text, and is used when reporting missing characters.
+fn count_char_width(
+    font: &FontStack,
+    policy: MissingGlyphPolicy,
+    text: &str,
+    offset: usize,
+) -> Result<u32, Error> {
     let mut width_sum: u32 = 0;
-    let iter = text.chars();
+    let iter = text.char_indices();
 
-    for c in iter {
-        match unifont.get(&(c as u32)) {
-            None => return Err(gen_missing_char_str(&c)),
-            Some(fc) => width_sum += fc.width as u32,
+    for (index, c) in iter {
+        if let Some(found) = get_glyph(font, policy, c, offset + index)? {
+            width_sum += found.glyph().width() as u32;
         }
     }
 
     Ok(width_sum)
 }
 
-fn gen_missing_char_str(c: &char) -> String {
-    format!(
-        "Embedded Unifont does not contain {} (code point: 0x{:x})",
-        c, *c as u32
-    )
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Mixed half- and full-width text for truncation tests
+    const MIXED: &str = "Hello, 世界! こんにちは world";
+
+    /// Returns a font in which every pixel of each character is set. Printable
+    /// ASCII and `…` are 8px wide, and the characters of `wide` are 16px wide,
+    /// like Unifont's, but the tests don't depend on which characters the
+    /// embedded Unifont was built with.
+    fn test_font(wide: &str) -> Font {
+        let narrow = (' '..='~').chain(Some('\u{2026}'));
+
+        let mut hex = String::new();
+        for c in narrow.chain(wide.chars()) {
+            let bitmap = if c.is_ascii() || c == '\u{2026}' {
+                "FF"
+            } else {
+                "FFFF"
+            };
+            hex += &format!("{:04X}:{}\n", c as u32, bitmap.repeat(16));
+        }
+
+        Font::from_hex_str(&hex).unwrap()
+    }
+
+    /// Returns a renderer which draws with `test_font`, covering `MIXED`.
+    fn renderer() -> SurfaceRenderer {
+        SurfaceRenderer::with_font(
+            Arc::new(test_font("世界こんにちは")),
+            Color::RGB(0, 0, 0),
+            Color::RGB(255, 255, 255),
+        )
+    }
+
+    /// Draws text to a one-byte-per-pixel buffer without SDL, returning each
+    /// row of pixels as `#`s and `.`s, with the unset pixels at the end of the
+    /// row left out.
+    fn draw_rows(
+        renderer: &SurfaceRenderer,
+        text: &str,
+    ) -> Result<Vec<String>, Error> {
+        let (width, height) = (64, 32);
+        let mut pixels = vec![0; (width * height) as usize];
+        let mut canvas = Canvas {
+            pixels: &mut pixels,
+            pitch: width,
+            bytes_per_pixel: 1,
+            width,
+            height,
+            fg_pixel: 1,
+        };
+        renderer.draw_raw(&mut canvas, text, 0, 0, width, height)?;
+
+        Ok(pixels
+            .chunks(width as usize)
+            .map(|row| {
+                let row: String = row
+                    .iter()
+                    .map(|&pixel| if pixel != 0 { '#' } else { '.' })
+                    .collect();
+                row.trim_end_matches('.').to_string()
+            })
+            .collect())
+    }
+
+    /// Returns the number of columns up to and including the rightmost one
+    /// that text is drawn to.
+    fn drawn_width(
+        renderer: &SurfaceRenderer,
+        text: &str,
+    ) -> Result<u32, Error> {
+        let rows = draw_rows(renderer, text)?;
+
+        Ok(rows.iter().map(|row| row.len() as u32).max().unwrap_or(0))
+    }
+
+    /// Checks that truncating text never produces a line wider than the
+    /// limit, unless even an empty line is wider.
+    fn assert_truncation_fits(renderer: &SurfaceRenderer, text: &str) {
+        let empty = renderer.measure_width("").unwrap();
+        let full = renderer.measure_width(text).unwrap();
+
+        for &mode in &[Truncation::Start, Truncation::Middle, Truncation::End] {
+            for max_width in 0..full + 16 {
+                let truncated =
+                    renderer.truncate(text, max_width, mode).unwrap();
+                let width = renderer.measure_width(&truncated).unwrap();
+
+                assert!(
+                    width <= max_width.max(empty),
+                    "{:?} truncated to {} is {:?}, {}px wide",
+                    mode,
+                    max_width,
+                    truncated,
+                    width
+                );
+
+                if max_width >= full {
+                    assert_eq!(truncated, text);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn truncate_fits_width() {
+        assert_truncation_fits(&renderer(), MIXED);
+    }
+
+    #[test]
+    fn truncate_fits_width_with_formatting() {
+        let mut renderer = renderer();
+
+        renderer.bold = true;
+        assert_truncation_fits(&renderer, MIXED);
+
+        renderer.italic = true;
+        assert_truncation_fits(&renderer, MIXED);
+
+        renderer.bold = false;
+        renderer.scale = 2;
+        assert_truncation_fits(&renderer, MIXED);
+    }
+
+    #[test]
+    fn truncate_keeps_ends() {
+        let renderer = renderer();
+        let text = "abcdefghij";
+
+        // Room for six characters, one of which is the ellipsis
+        let start = renderer.truncate(text, 48, Truncation::Start).unwrap();
+        let middle = renderer.truncate(text, 48, Truncation::Middle).unwrap();
+        let end = renderer.truncate(text, 48, Truncation::End).unwrap();
+
+        assert_eq!(start, "\u{2026}fghij");
+        assert_eq!(middle, "ab\u{2026}hij");
+        assert_eq!(end, "abcde\u{2026}");
+    }
+
+    #[test]
+    fn truncate_with_wide_ellipsis() {
+        let mut renderer = renderer();
+        renderer.ellipsis = "[...]".to_string();
+
+        // The ellipsis is left out if it doesn't fit on its own
+        let truncated = renderer.truncate(MIXED, 32, Truncation::End).unwrap();
+        assert_eq!(truncated, "Hell");
+
+        assert_truncation_fits(&renderer, MIXED);
+    }
+
+    #[test]
+    fn missing_glyph_policy_is_applied_consistently() {
+        let text = "a\u{e000}b";
+        let mut renderer = renderer();
+
+        let policies = [
+            (MissingGlyphPolicy::Skip, 16),
+            (MissingGlyphPolicy::Replace('b'), 24),
+            (MissingGlyphPolicy::HexBox, 32),
+        ];
+        for &(policy, width) in &policies {
+            renderer.missing_glyph = policy;
+            let font = renderer.font().unwrap();
+
+            let measured = renderer.measure_width(text).unwrap();
+            let counted = count_char_width(&font, policy, text, 0).unwrap();
+            let chars = renderer.char_widths(&font, text, &(0..text.len()));
+            let summed: u32 = chars.unwrap().iter().map(|c| c.2).sum();
+
+            assert_eq!(measured, width, "{:?}", policy);
+            assert_eq!(counted, width, "{:?}", policy);
+            assert_eq!(summed, width, "{:?}", policy);
+            assert_eq!(drawn_width(&renderer, text).unwrap(), width);
+
+            // Wrapping measures each character the same way
+            renderer.wrap_width = Some(width);
+            assert_eq!(renderer.layout(text).unwrap().len(), 1);
+            renderer.wrap_width = Some(width - 1);
+            assert_eq!(renderer.layout(text).unwrap().len(), 2);
+            renderer.wrap_width = None;
+        }
+
+        renderer.missing_glyph = MissingGlyphPolicy::Error;
+        let missing = |error: Option<Error>| match error {
+            Some(Error::MissingGlyph { character, index }) => {
+                character == '\u{e000}' && index == 1
+            }
+            _ => false,
+        };
+
+        assert!(missing(renderer.measure_width(text).err()));
+        assert!(missing(drawn_width(&renderer, text).err()));
+        renderer.wrap_width = Some(64);
+        assert!(missing(renderer.layout(text).err()));
+
+        // Replacements which are missing too are reported
+        renderer.missing_glyph = MissingGlyphPolicy::Replace('\u{e001}');
+        assert!(missing(renderer.measure_width(text).err()));
+    }
+
+    #[test]
+    fn mixed_heights_share_a_baseline() {
+        // 4px above the baseline and 4px below it, against the 16px font's
+        // 14px and 2px
+        let mut small = Font::with_height(8);
+        small.set_ascent(4);
+        small.insert('a' as u32, Glyph::new(8, &[0xff; 8])).unwrap();
+
+        let mut renderer = renderer();
+        renderer.font = Some(Arc::new(small));
+        renderer.fallbacks.push(Arc::new(test_font("世")));
+
+        let font = renderer.font().unwrap();
+        assert_eq!((font.ascent, font.height), (14, 18));
+        assert_eq!(renderer.measure_height("a世").unwrap(), 18);
+        assert_eq!(renderer.measure_height("a\n世").unwrap(), 36);
+
+        let small_glyph = get_glyph(&font, renderer.missing_glyph, 'a', 0);
+        let large_glyph = get_glyph(&font, renderer.missing_glyph, '世', 1);
+        assert_eq!(small_glyph.unwrap().unwrap().top(font.ascent), 10);
+        assert_eq!(large_glyph.unwrap().unwrap().top(font.ascent), 0);
+
+        let rows = draw_rows(&renderer, "a世").unwrap();
+        let full = "#".repeat(24);
+        for (y, row) in rows.iter().enumerate() {
+            let expected = match y {
+                0..=9 => "........################",
+                10..=15 => &full,
+                16..=17 => "########",
+                _ => "",
+            };
+            assert_eq!(row, expected, "row {}", y);
+        }
+    }
+
+    /// Wraps a single line of text, in which ASCII characters are 8px wide and
+    /// everything else is 16px wide.
+    fn wrap(text: &str, available: u32) -> Vec<&str> {
+        let chars: Vec<(usize, char, u32)> = text
+            .char_indices()
+            .map(|(i, c)| (i, c, if c.is_ascii() { 8 } else { 16 }))
+            .collect();
+
+        let mut lines = Vec::new();
+        wrap_line(&chars, text.len(), available, &mut lines);
+
+        lines.into_iter().map(|line| &text[line]).collect()
+    }
+
+    #[test]
+    fn wrap_fits_line() {
+        assert_eq!(wrap("hello world", 88), ["hello world"]);
+    }
+
+    #[test]
+    fn wrap_at_spaces() {
+        assert_eq!(wrap("hello world", 64), ["hello", "world"]);
+    }
+
+    #[test]
+    fn wrap_trims_trailing_spaces() {
+        assert_eq!(wrap("ab   cd", 32), ["ab", "cd"]);
+    }
+
+    #[test]
+    fn wrap_carries_word_over() {
+        // The rest of a word which is too long for a line of its own is
+        // carried over onto the next line
+        assert_eq!(wrap("abc defghij", 48), ["abc", "defghi", "j"]);
+    }
+
+    #[test]
+    fn wrap_avoids_blank_lines() {
+        // Breaking at the space would leave nothing but spaces on the first
+        // line, so it's broken between characters instead
+        assert_eq!(wrap("  abcdef", 24), ["  a", "bcd", "ef"]);
+    }
+
+    #[test]
+    fn wrap_follows_kinsoku() {
+        assert_eq!(wrap("あいうえ。", 64), ["あいう", "え。"]);
+        assert_eq!(wrap("あいう「え」", 64), ["あいう", "「え」"]);
+    }
+
+    #[test]
+    fn wrap_to_zero_width() {
+        // Every character ends up on a line of its own
+        assert_eq!(wrap("ab cd", 0), ["a", "b", "c", "d"]);
+        assert_eq!(wrap("日本", 0), ["日", "本"]);
+    }
+
+    #[test]
+    fn check_finds_missing_characters() {
+        let text = "a\u{e000}\r\nb\u{10ffff}";
+        let missing = renderer().check(text).unwrap();
+
+        assert_eq!(missing.len(), 2);
+        assert_eq!(missing[0].character, '\u{e000}');
+        assert_eq!((missing[0].index, missing[0].char_index), (1, 1));
+        assert_eq!((missing[1].index, missing[1].char_index), (7, 5));
+        assert_eq!(missing[1].feature, None);
+    }
+
+    #[test]
+    fn check_suggests_features_only_for_unifont() {
+        let emoji = "\u{1f600}";
+        let expected = unifont::feature_for(0x1f600);
+
+        let missing = |renderer: &SurfaceRenderer| {
+            renderer.check(emoji).unwrap().first().map(|m| m.feature)
+        };
+
+        let mut renderer =
+            SurfaceRenderer::new(Color::RGB(0, 0, 0), Color::RGB(0, 0, 0));
+        if expected.is_some() {
+            assert_eq!(missing(&renderer), Some(expected));
+        }
+
+        renderer.font = Some(Arc::new(Font::new()));
+        assert_eq!(missing(&renderer), Some(None));
+
+        renderer.fallbacks.push(unifont::get_unifont().unwrap());
+        if expected.is_some() {
+            assert_eq!(missing(&renderer), Some(expected));
+        }
+
+        renderer.fallbacks.clear();
+        renderer.font = Some(unifont::get_unifont().unwrap());
+        if expected.is_some() {
+            assert_eq!(missing(&renderer), Some(expected));
+        }
+    }
 }
@@ -1,161 +1,287 @@
-/// Contains the xzipped embedded Unifont planes which have been selected using
-/// cargo features. The first time that the font is used, the embedded font
-/// contents are decompressed and parsed into a fast hashmap structure which
-/// makes character lookup quick.
+/// Contains the parts of the embedded Unifont which have been selected using
+/// cargo features. The .hex files are parsed into an xz-compressed binary glyph
+/// table at build time, so the first time that the font is used, the table only
+/// needs to be decompressed and copied into a compact page table which makes
+/// character lookup quick.
 ///
-/// None of the functions here should need to be invoked manually - it is
-/// automatically handled by the renderer.
-use std::boxed::Box;
-use std::collections::HashMap;
-use std::io::{BufRead, BufReader, Cursor, Error, ErrorKind};
+/// The renderers load the font automatically the first time that they need
+/// it, but it can be loaded ahead of time with `preload` (or
+/// `preload_in_background`, to show a loading screen in the meantime), and its
+/// memory released again with `unload`.
+use std::cell::Cell;
+use std::io::{self, BufReader, Read};
+use std::panic;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::{self, JoinHandle};
 
-use lzma::LzmaError;
 use lzma::LzmaReader;
 
-// NOTE: The following files are generated by build.rs
-
-#[cfg(feature = "plane-0")]
-/// Contains the raw file contents of the Unifont plane 0 font
-const PLANE_0_RAW: &'static [u8] =
-    include_bytes!(concat!(env!("OUT_DIR"), "/unifont-11.0.02.hex.xz"));
-
-#[cfg(feature = "plane-1")]
-/// Contains the raw file contents of the Unifont plane 1 font
-const PLANE_1_RAW: &'static [u8] =
-    include_bytes!(concat!(env!("OUT_DIR"), "/unifont_upper-11.0.02.hex.xz"));
-
-/// The container type which relates Unicode code points with font character
-/// definitions.
-pub type FontChars = HashMap<u32, FontChar>;
-
-/// Contains parsed character definitions. A hashmap is utilised for fast
-/// look-ups, since there are often gaps between defined characters in the
-/// Unifont files.
-static mut UNIFONT: Option<Box<FontChars>> = None;
-
-/// Describes a single character in the font.
-pub struct FontChar {
-    /// Width, either 8px (half-width) or 16px (full-width)
-    pub width: u8,
-    /// Array of lines of the character's form. MSB is unused for half-width
-    /// characters
-    pub bitmap: [u16; 16],
-}
-
-/// Decompresses a supplied embedded font file, before parsing the decompressed
-/// result using `initialise_from_str`.
-fn initialise_generic(font: &[u8]) -> Result<(), LzmaError> {
-    // Mutate hashmap (we assume that if it's uninitialised, nobody else is
-    // using it anyway)
-    let hashmap = unsafe {
-        match UNIFONT {
-            None => return Err(LzmaError::Other),
-            Some(ref mut hashmap) => hashmap,
-        }
-    };
+pub use font::{FontChar, FontChars, Glyph};
 
-    // Read decompressed embedded font line-by-line
-    let cur = Cursor::new(font);
-    let decomp = LzmaReader::new_decompressor(cur)?;
-    let reader = BufReader::new(decomp);
-    let lines = reader.lines();
-
-    // Returned as the Result value if the font file is malformed
-    let corrupt_file_err = Err(LzmaError::Io(Error::new(
-        ErrorKind::InvalidData,
-        "font file malformed",
-    )));
-
-    for l in lines {
-        let line = l.unwrap();
-        let mut split = line.split(":");
-
-        // Parse code point of current character
-        let codepoint = match split.next() {
-            Some(cp) => match u32::from_str_radix(cp, 16) {
-                Ok(cp_num) => cp_num,
-                Err(_) => return corrupt_file_err,
-            },
-
-            None => return corrupt_file_err,
-        };
+use error::Error;
+use features::GROUPS;
 
-        // Retrieve bitmap string
-        let bitmap = match split.next() {
-            Some(bmp) => bmp,
-            None => return corrupt_file_err,
-        };
+use font::{Font, FontInfo};
 
-        // Determine if current character is half or full-width
-        let char_count = bitmap.chars().count();
-        let char_width = char_count / 4;
+// NOTE: The following file is generated by build.rs
 
-        if char_width != 8 && char_width != 16 {
-            return corrupt_file_err;
-        }
+/// Contains the xz-compressed, pre-parsed glyph table of the parts of Unifont
+/// selected by the enabled cargo features
+const GLYPHS: &[u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/unifont.glyphs.xz"));
 
-        // Number of chars per pixel row
-        let row_width = char_width / 4;
+/// Contains the parsed embedded Unifont whilst it's loaded. Only ever locked
+/// for writing to store or remove the finished font, so that the readers on
+/// every draw never have to wait for one another.
+static UNIFONT: RwLock<Option<Arc<Font>>> = RwLock::new(None);
 
-        // Create and load binary bitmap array
-        let mut bitmap_arr: [u16; 16] = [0; 16];
-        let mut bitmap_i = 0;
+/// Held whilst the embedded font is being loaded, so that concurrent first
+/// uses from several threads only load it once.
+static UNIFONT_LOAD: Mutex<()> = Mutex::new(());
 
-        for i in 0..char_count / row_width {
-            let line = &bitmap[(i * row_width)..(i * row_width) + row_width];
+/// Returns the loaded font, if any. A panic can't leave a half-written value
+/// behind, so a poisoned lock can be safely reused.
+fn loaded() -> Option<Arc<Font>> {
+    match UNIFONT.read() {
+        Ok(unifont) => unifont.clone(),
+        Err(poisoned) => poisoned.into_inner().clone(),
+    }
+}
 
-            // Convert hex line bit pattern to binary
-            bitmap_arr[bitmap_i] = match u16::from_str_radix(line, 16) {
-                Ok(bit_line) => bit_line,
-                Err(_) => return corrupt_file_err,
-            };
+/// Replaces the loaded font.
+fn set_loaded(font: Option<Arc<Font>>) {
+    match UNIFONT.write() {
+        Ok(mut unifont) => *unifont = font,
+        Err(poisoned) => *poisoned.into_inner() = font,
+    }
+}
 
-            bitmap_i += 1;
-        }
+/// Returns the loaded font, first loading the embedded glyph tables if
+/// necessary. `progress` is called with the fraction of `GLYPHS` read so far.
+fn load<F>(mut progress: F) -> Result<Arc<Font>, Error>
+where
+    F: FnMut(f32),
+{
+    if let Some(font) = loaded() {
+        return Ok(font);
+    }
 
-        // We have all the information we need; insert the completed character
-        // into our global hashmap.
-        hashmap.insert(
-            codepoint,
-            FontChar {
-                width: char_width as u8,
-                bitmap: bitmap_arr,
-            },
-        );
+    let _guard = match UNIFONT_LOAD.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+
+    // Another thread may have finished loading whilst we were waiting
+    if let Some(font) = loaded() {
+        return Ok(font);
     }
 
-    Ok(())
+    // The table is decompressed as it's read, so the whole decompressed table
+    // is never held in memory alongside the font
+    let read = Cell::new(0);
+    let compressed = Counted {
+        bytes: GLYPHS,
+        read: &read,
+    };
+    let table = BufReader::new(LzmaReader::new_decompressor(compressed)?);
+
+    let mut font = Font::new();
+    font.load_table(table, || {
+        progress(read.get() as f32 / GLYPHS.len() as f32)
+    })?;
+    font.set_info(FontInfo {
+        name: known(env!("SDL2_UNIFONT_NAME")),
+        version: known(env!("SDL2_UNIFONT_VERSION")),
+        license: known(env!("SDL2_UNIFONT_LICENSE")),
+    });
+
+    let font = Arc::new(font);
+    set_loaded(Some(font.clone()));
+
+    Ok(font)
 }
 
-/// Called the first time that a reference to the `UNIFONT` hashmap is requested,
-/// in order to decompress and parse the embedded, xzipped .hex contents
-pub unsafe fn initialise_unifont() -> Result<(), LzmaError> {
-    // Initialise UNIFONT variable, since it _should_ be None at the moment
-    UNIFONT = Some(Box::new(HashMap::new()));
+/// Reads from a slice of bytes, keeping count of how many have been read, so
+/// that the progress of loading `GLYPHS` can be reported whilst it's being
+/// decompressed.
+struct Counted<'a> {
+    /// The bytes which are still to be read
+    bytes: &'a [u8],
+    /// Number of bytes read so far
+    read: &'a Cell<usize>,
+}
 
-    #[cfg(feature = "plane-0")]
-    initialise_generic(PLANE_0_RAW)?;
+impl<'a> Read for Counted<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = self.bytes.read(buf)?;
+        self.read.set(self.read.get() + count);
+        Ok(count)
+    }
+}
 
-    #[cfg(feature = "plane-1")]
-    initialise_generic(PLANE_1_RAW)?;
+/// Converts a description of the embedded font, set by build.rs, to `None` if
+/// it's empty.
+fn known(value: &str) -> Option<String> {
+    match value {
+        "" => None,
+        value => Some(value.to_string()),
+    }
+}
+
+/// Loads the embedded glyph tables, so that the first text drawn doesn't have
+/// to wait for them. Does nothing if the font is already loaded. Safe to call
+/// from any thread; concurrent callers block until the first one has finished.
+pub fn preload() -> Result<(), Error> {
+    load(|_| ())?;
 
     Ok(())
 }
 
-/// Returns a safe, immutable reference to the parsed Unifont hashmap structure.
-/// Initialises said structure if it hasn't already been.
-pub fn get_unifont<'a>() -> Result<&'a FontChars, LzmaError> {
-    unsafe {
-        match UNIFONT {
-            None => match initialise_unifont() {
-                Ok(_) => get_unifont(),
-                Err(e) => {
-                    UNIFONT = None;
-                    return Err(e);
-                }
-            },
-
-            Some(ref unifont) => Ok(unifont),
+/// Loads the embedded glyph tables on a new thread, returning a handle which
+/// can be polled for the thread's progress, e.g. by a loading screen. Text
+/// drawn whilst the font is loading waits for it to finish.
+pub fn preload_in_background() -> Preload {
+    let progress = Arc::new(AtomicU32::new(0.0f32.to_bits()));
+
+    let loaded = progress.clone();
+    let thread = thread::spawn(move || {
+        load(|fraction| loaded.store(fraction.to_bits(), Ordering::Relaxed))?;
+
+        // The font may have already been loaded by someone else
+        loaded.store(1.0f32.to_bits(), Ordering::Relaxed);
+        Ok(())
+    });
+
+    Preload { progress, thread }
+}
+
+/// Releases the loaded font. Its memory is freed as soon as nothing else holds
+/// on to it, i.e. once no renderer is drawing with it, and it has been removed
+/// from any renderers' fallback fonts. The font is loaded again the next time
+/// that it's needed.
+pub fn unload() {
+    set_loaded(None);
+}
+
+/// Returns whether the font is currently loaded. Returns `false` whilst it's
+/// still being loaded.
+pub fn is_loaded() -> bool {
+    loaded().is_some()
+}
+
+/// Loads the embedded glyph tables, if they haven't already been.
+#[deprecated(note = "use `preload` instead")]
+pub fn initialise_unifont() -> Result<(), Error> {
+    preload()
+}
+
+/// Returns a shared handle to the parsed Unifont font structure, loading it if
+/// it isn't already loaded. Holding on to the handle (e.g. by adding it to a
+/// renderer's fallback fonts) keeps the font in memory, even once `unload` has
+/// been called.
+pub fn get_unifont() -> Result<Arc<Font>, Error> {
+    load(|_| ())
+}
+
+/// Returns the cargo feature which would embed the supplied character in
+/// Unifont: the group of scripts containing it, or otherwise the plane
+/// feature. Returns `None` if a feature which selects the character is already
+/// enabled, since Unifont doesn't define the character then, or if no feature
+/// selects it (i.e. it's outside of the planes that Unifont's files cover).
+/// This doesn't account for `SDL2_UNIFONT_SUBSET`, which replaces the features,
+/// or `SDL2_UNIFONT_HEX`.
+pub fn feature_for(codepoint: u32) -> Option<&'static str> {
+    // The upper file holds plane 14's tag and variation selector characters,
+    // as well as plane 1
+    let plane = match codepoint {
+        0..=0xffff => "plane-0",
+        0x10000..=0x1ffff | 0xe0000..=0xeffff => "plane-1",
+        _ => return None,
+    };
+    let group = GROUPS
+        .iter()
+        .find(|(_, ranges)| {
+            ranges
+                .iter()
+                .any(|&(first, last)| first <= codepoint && codepoint <= last)
+        })
+        .map(|&(feature, _)| feature);
+
+    if group.into_iter().chain(Some(plane)).any(feature_enabled) {
+        return None;
+    }
+
+    Some(group.unwrap_or(plane))
+}
+
+/// Returns whether the named cargo feature was enabled for this build, as
+/// listed by build.rs.
+fn feature_enabled(feature: &str) -> bool {
+    env!("SDL2_UNIFONT_FEATURES")
+        .split(',')
+        .any(|enabled| enabled == feature)
+}
+
+/// A handle to the thread started by `preload_in_background`.
+pub struct Preload {
+    /// Fraction of `GLYPHS` read so far, as the bits of an `f32`
+    progress: Arc<AtomicU32>,
+    thread: JoinHandle<Result<(), Error>>,
+}
+
+impl Preload {
+    /// Returns how much of the font has been loaded so far, from 0.0 to 1.0.
+    pub fn progress(&self) -> f32 {
+        f32::from_bits(self.progress.load(Ordering::Relaxed))
+    }
+
+    /// Returns whether the thread has finished loading the font.
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Blocks until the thread has finished loading the font, and returns the
+    /// result of loading it.
+    pub fn wait(self) -> Result<(), Error> {
+        match self.thread.join() {
+            Ok(result) => result,
+            Err(cause) => panic::resume_unwind(cause),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preload_and_unload() {
+        let loading = preload_in_background();
+        loading.wait().unwrap();
+        assert!(is_loaded());
+
+        // Handles stay usable after the font is unloaded, and it's loaded
+        // again when next needed
+        let font = get_unifont().unwrap();
+        let count = font.characters().count();
+        unload();
+        assert_eq!(font.characters().count(), count);
+        assert_eq!(get_unifont().unwrap().characters().count(), count);
+    }
+
+    #[test]
+    fn suggests_features_only_within_unifont() {
+        // Plane 14 is in the same file as plane 1
+        let plane_1 = if cfg!(feature = "plane-1") {
+            None
+        } else {
+            Some("plane-1")
+        };
+        assert_eq!(feature_for(0xe0001), plane_1);
+
+        // Unifont 11 doesn't cover planes 2 and 3, so no feature can help
+        assert_eq!(feature_for(0x20000), None);
+        assert_eq!(feature_for(0x10ffff), None);
+    }
+}